@@ -0,0 +1,161 @@
+//
+// Copyright (c) The Holo Core Contributors
+//
+// SPDX-License-Identifier: MIT
+//
+
+use yang4::context::Context;
+use yang4::schema::{SchemaNode, SchemaNodeKind};
+
+// Configuration path given as CLI command words, resolved into a data path.
+#[derive(Debug)]
+pub struct CliPath<'a> {
+    // Schema node of the last path element.
+    pub snode: SchemaNode<'a>,
+    // Data path of the parent of the last path element.
+    pub parent_path: String,
+    // Data path of the last path element.
+    pub data_path: String,
+    // Whether the path matches multiple list entries, either because a key
+    // value is "*" or because the keys of the last list are missing.
+    pub wildcard: bool,
+}
+
+// ===== global functions =====
+
+// Resolves the given CLI command words into a data path, starting from the
+// given schema node and data path (or from the top level when none is given).
+//
+// Resolution stops at the first word that doesn't match any configuration
+// node, or after a leaf or leaf-list. The unresolved words are returned along
+// with the resolved path, if any.
+pub fn resolve<'a, 'b>(
+    yang_ctx: &'a Context,
+    base: Option<(SchemaNode<'a>, &str)>,
+    words: &'b [String],
+) -> (Option<CliPath<'a>>, &'b [String]) {
+    let mut cli_path: Option<CliPath<'a>> = None;
+    let mut words = words;
+
+    while let Some((next, rest)) =
+        resolve_next(yang_ctx, cli_path.as_ref(), base.as_ref(), words)
+    {
+        words = rest;
+        let terminal = matches!(
+            next.snode.kind(),
+            SchemaNodeKind::Leaf | SchemaNodeKind::LeafList
+        );
+        cli_path = Some(next);
+        if terminal {
+            break;
+        }
+    }
+
+    (cli_path, words)
+}
+
+// Resolves a single path element (a node name, followed by the key values when
+// it's a list) relative to the given CLI path, or to the base schema node and
+// data path when no CLI path is given.
+pub fn resolve_next<'a, 'b>(
+    yang_ctx: &'a Context,
+    cli_path: Option<&CliPath<'a>>,
+    base: Option<&(SchemaNode<'a>, &str)>,
+    words: &'b [String],
+) -> Option<(CliPath<'a>, &'b [String])> {
+    let (parent, mut data_path, mut wildcard) = match (cli_path, base) {
+        (Some(cli_path), _) => (
+            Some(cli_path.snode.clone()),
+            cli_path.data_path.clone(),
+            cli_path.wildcard,
+        ),
+        (None, Some((snode, data_path))) => {
+            (Some(snode.clone()), data_path.to_string(), false)
+        }
+        (None, None) => (None, String::new(), false),
+    };
+
+    let (word, mut words) = words.split_first()?;
+    let snode = find_child(yang_ctx, parent.as_ref(), word)?;
+    let parent_path = data_path.clone();
+    data_path += &format!("/{}:{}", snode.module().name(), snode.name());
+
+    // Add list keys.
+    if snode.kind() == SchemaNodeKind::List {
+        for key in snode.list_keys() {
+            let Some((value, rest)) = words.split_first() else {
+                wildcard = true;
+                break;
+            };
+            words = rest;
+            if value == "*" {
+                wildcard = true;
+                continue;
+            }
+            data_path += &key_predicate(key.name(), value);
+        }
+    }
+
+    let cli_path = CliPath {
+        snode,
+        parent_path,
+        data_path,
+        wildcard,
+    };
+    Some((cli_path, words))
+}
+
+// Builds a predicate matching the given key value.
+pub fn key_predicate(name: &str, value: &str) -> String {
+    if value.contains('\'') {
+        format!("[{}=\"{}\"]", name, value)
+    } else {
+        format!("[{}='{}']", name, value)
+    }
+}
+
+// ===== helper functions =====
+
+// Finds the configuration node with the given name among the children of the
+// given schema node (or among the top-level nodes when none is given), looking
+// inside choices and cases.
+fn find_child<'a>(
+    yang_ctx: &'a Context,
+    parent: Option<&SchemaNode<'a>>,
+    name: &str,
+) -> Option<SchemaNode<'a>> {
+    let children: Box<dyn Iterator<Item = SchemaNode<'a>>> = match parent {
+        Some(parent) => Box::new(parent.children()),
+        None => {
+            Box::new(yang_ctx.modules(true).flat_map(|module| module.data()))
+        }
+    };
+
+    for snode in children.filter(|snode| snode.is_config()) {
+        if snode.is_schema_only() {
+            if let Some(snode) = find_child(yang_ctx, Some(&snode), name) {
+                return Some(snode);
+            }
+        } else if snode.name() == name && !snode.is_list_key() {
+            return Some(snode);
+        }
+    }
+
+    None
+}
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_predicate_quoting() {
+        assert_eq!(key_predicate("name", "eth0"), "[name='eth0']");
+        assert_eq!(key_predicate(".", "a b"), "[.='a b']");
+        assert_eq!(key_predicate("name", "it's"), "[name=\"it's\"]");
+        assert_eq!(key_predicate("name", "say \"hi\""), "[name='say \"hi\"']");
+        assert_eq!(key_predicate("name", ""), "[name='']");
+    }
+}
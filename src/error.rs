@@ -5,14 +5,35 @@
 //
 
 use indextree::NodeId;
+use yang4::context::Context;
+use yang4::ffi;
+
+use crate::token_yang;
 
 #[derive(Debug)]
 pub enum Error {
     Parser(ParserError),
     EditConfig(yang4::Error),
     ValidateConfig(yang4::Error),
+    Rebase(yang4::Error),
+    InvalidPath(String),
+    Validation(Vec<ValidationError>),
     Callback(String),
     Backend(tonic::Status),
+    InvalidResponse(&'static str),
+    Timeout,
+    Interrupted,
+    Datastore(String),
+    Connection(String),
+    Offline,
+}
+
+// Configuration validation error, reported either by libyang or by holod.
+#[derive(Debug)]
+pub struct ValidationError {
+    pub message: String,
+    pub data_path: Option<String>,
+    pub apptag: Option<String>,
 }
 
 #[derive(Debug)]
@@ -20,6 +41,7 @@ pub enum ParserError {
     NoMatch(String),
     Incomplete(NodeId),
     Ambiguous(Vec<NodeId>),
+    UnterminatedQuote,
 }
 
 // ===== impl Error =====
@@ -34,18 +56,132 @@ impl std::fmt::Display for Error {
             Error::ValidateConfig(error) => {
                 write!(f, "failed to validate configuration: {}", error)
             }
+            Error::Validation(errors) => {
+                write!(f, "failed to validate configuration:")?;
+                for error in errors {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+            Error::Rebase(error) => {
+                write!(f, "failed to rebase candidate configuration: {}", error)
+            }
+            Error::InvalidPath(error) => write!(f, "invalid path: {}", error),
             Error::Callback(error) => {
                 write!(f, "failed to execute command: {}", error)
             }
             Error::Backend(error) => {
                 write!(f, "{}", error)
             }
+            Error::InvalidResponse(error) => {
+                write!(f, "invalid response from holod: {}", error)
+            }
+            Error::Timeout => write!(f, "request timed out"),
+            Error::Interrupted => write!(f, "interrupted"),
+            Error::Datastore(error) => {
+                write!(f, "failed to access local datastore: {}", error)
+            }
+            Error::Connection(error) => write!(f, "{}", error),
+            Error::Offline => {
+                write!(f, "operation not available in offline mode")
+            }
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+// ===== impl ValidationError =====
+
+impl ValidationError {
+    // Collects all errors stored in the YANG context.
+    //
+    // Falls back to the given error when none is stored, which happens unless
+    // libyang was told to store all errors (`LY_LOSTORE`).
+    pub fn from_yang(
+        yang_ctx: &Context,
+        error: yang4::Error,
+    ) -> Vec<ValidationError> {
+        let mut errors = vec![];
+        let Some(ctx) = raw_context(yang_ctx) else {
+            return vec![error.into()];
+        };
+        let mut eitem = unsafe { ffi::ly_err_first(ctx) };
+        while !eitem.is_null() {
+            let item = unsafe { &*eitem };
+            if item.level == ffi::LY_LOG_LEVEL::LY_LLERR {
+                errors.push(ValidationError {
+                    message: char_ptr_to_opt_string(item.msg)
+                        .unwrap_or_else(|| "unknown error".to_owned()),
+                    data_path: char_ptr_to_opt_string(item.data_path),
+                    apptag: char_ptr_to_opt_string(item.apptag),
+                });
+            }
+            eitem = item.next;
+        }
+        unsafe { ffi::ly_err_clean(ctx, std::ptr::null_mut()) };
+
+        if errors.is_empty() {
+            errors.push(error.into());
+        }
+        errors
+    }
+
+    // Discards the errors stored by libyang, so that errors left over from
+    // earlier operations aren't reported as validation errors.
+    pub fn clear_yang(yang_ctx: &Context) {
+        if let Some(ctx) = raw_context(yang_ctx) {
+            unsafe { ffi::ly_err_clean(ctx, std::ptr::null_mut()) };
+        }
+    }
+
+    // Parses the validation errors reported by holod, one per line.
+    //
+    // holod reports them as free-form text, so the messages are shown as they
+    // are, without a data path.
+    pub fn from_status(status: &tonic::Status) -> Vec<ValidationError> {
+        status
+            .message()
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| ValidationError {
+                message: line.to_owned(),
+                data_path: None,
+                apptag: None,
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if let Some(apptag) = &self.apptag {
+            write!(f, " ({})", apptag)?;
+        }
+        if let Some(data_path) = &self.data_path {
+            write!(f, "\n    path: {}", data_path)?;
+            write!(
+                f,
+                "\n    command: {}",
+                token_yang::data_path_to_cli_path(data_path)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl From<yang4::Error> for ValidationError {
+    fn from(error: yang4::Error) -> ValidationError {
+        ValidationError {
+            message: error.to_string(),
+            data_path: error.path,
+            apptag: error.apptag,
+        }
+    }
+}
+
 // ===== impl ParserError =====
 
 impl std::fmt::Display for ParserError {
@@ -56,8 +192,32 @@ impl std::fmt::Display for ParserError {
             }
             ParserError::Incomplete(_) => write!(f, "incomplete command"),
             ParserError::Ambiguous(_) => write!(f, "ambiguous command"),
+            ParserError::UnterminatedQuote => {
+                write!(f, "unterminated quoted string")
+            }
         }
     }
 }
 
 impl std::error::Error for ParserError {}
+
+// ===== helper functions =====
+
+// Returns the raw libyang context, which isn't exposed by the bindings, through
+// one of its modules.
+pub fn raw_context(yang_ctx: &Context) -> Option<*mut ffi::ly_ctx> {
+    let module = yang_ctx.modules(false).next()?;
+    Some(unsafe { (*module.as_raw()).ctx })
+}
+
+// Converts C String to optional owned string.
+fn char_ptr_to_opt_string(
+    c_str: *const std::os::raw::c_char,
+) -> Option<String> {
+    if c_str.is_null() {
+        None
+    } else {
+        let c_str = unsafe { std::ffi::CStr::from_ptr(c_str) };
+        Some(c_str.to_string_lossy().into_owned())
+    }
+}
@@ -4,23 +4,69 @@
 // SPDX-License-Identifier: MIT
 //
 
+use std::collections::BTreeMap;
 use std::ffi::{CStr, CString};
-use std::os::raw::{c_char, c_void};
+use std::os::raw::{c_char, c_int, c_void};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
 
+use hyper_util::rt::TokioIo;
 use proto::northbound_client::NorthboundClient;
+use serde::{Deserialize, Serialize};
+use tokio::net::UnixStream;
+use tonic::metadata::{AsciiMetadataValue, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{
+    Certificate, Channel, ClientTlsConfig, Endpoint, Identity, Uri,
+};
+use tower::service_fn;
 use yang4::data::{
     Data, DataDiffFlags, DataFormat, DataPrinterFlags, DataTree,
 };
 use yang4::ffi;
 
-use crate::YANG_MODULES_DIR;
-use crate::error::Error;
+use crate::YANG_CACHE_DIR;
+use crate::error::{Error, ValidationError};
 
 pub mod proto {
     tonic::include_proto!("holo");
 }
 
 type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
+type Client = NorthboundClient<InterceptedService<Channel, AuthInterceptor>>;
+
+// Maximum number of reconnection attempts after the connection to holod is
+// lost, and the delay before the first attempt (doubled on each attempt).
+const RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(200);
+
+// File marking a directory as a YANG modules cache created by holo-cli, so that
+// it's safe to clear.
+pub const CACHE_SENTINEL: &str = ".holo-cli-cache";
+
+// Cache directory used when holod doesn't advertise its version.
+const CACHE_UNVERSIONED: &str = "unversioned";
+
+// File listing the YANG modules advertised by holod, saved along with the
+// cached modules so that offline mode loads the same module set.
+pub const CACHE_MODULE_LIST: &str = "modules.toml";
+
+// Prefix of the files indexing the configuration commands generated from a
+// YANG module set, saved along with the cached modules.
+pub const CACHE_COMMAND_INDEX_PREFIX: &str = "commands-";
+
+// How often to check whether the user pressed Ctrl-C during a request.
+const INTERRUPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Whether the user pressed Ctrl-C since the SIGINT handler was installed.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// Number of requests in progress, and the SIGINT action to restore once they
+// all complete.
+static INTERRUPT_STATE: Mutex<(usize, Option<libc::sigaction>)> =
+    Mutex::new((0, None));
 
 // The order of the fields in this struct is important. They must be ordered
 // such that when `Client` is dropped the client is dropped before the runtime.
@@ -28,14 +74,130 @@ type StdError = Box<dyn std::error::Error + Send + Sync + 'static>;
 // fields in declaration order.
 #[derive(Debug)]
 pub struct GrpcClient {
-    client: NorthboundClient<tonic::transport::Channel>,
+    client: Client,
+    connected: bool,
+    reconnected: bool,
+    timeout: Option<Duration>,
+    capabilities: Option<proto::CapabilitiesResponse>,
+    cache_dir: Option<String>,
+    cache_holo_modules: bool,
+    prefetched: BTreeMap<String, (String, String)>,
+    interceptor: AuthInterceptor,
+    username: Option<String>,
+    endpoint: Endpoint,
+    unix_socket: Option<String>,
     runtime: tokio::runtime::Runtime,
 }
 
+// Options used to establish connections to holod.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectOptions {
+    pub tls: Option<TlsOptions>,
+    pub credentials: Credentials,
+    pub timeout: Option<Duration>,
+}
+
+// TLS connection options.
+#[derive(Clone, Debug, Default)]
+pub struct TlsOptions {
+    // CA certificate bundle used to verify the server (PEM).
+    pub ca_cert: Option<String>,
+    // Client certificate and private key used for mutual TLS (PEM).
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    // Domain name used for SNI and server certificate verification.
+    pub domain: Option<String>,
+}
+
+// Operator credentials sent along with all gRPC requests.
+#[derive(Clone, Debug, Default)]
+pub struct Credentials {
+    pub token: Option<String>,
+    pub username: Option<String>,
+}
+
+// gRPC interceptor that attaches the operator credentials as request
+// metadata.
+#[derive(Clone, Debug)]
+struct AuthInterceptor {
+    authorization: Option<AsciiMetadataValue>,
+    username: Option<AsciiMetadataValue>,
+}
+
+// YANG modules advertised by holod, along with their enabled features.
+//
+// Example:
+//
+//   [[module]]
+//   name = "ietf-routing"
+//   revision = "2018-03-13"
+//   features = ["router-id"]
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ModuleList {
+    #[serde(rename = "module", default)]
+    pub modules: Vec<ModuleListEntry>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ModuleListEntry {
+    pub name: String,
+    pub revision: Option<String>,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+// Catches SIGINT while requests are in progress, so that Ctrl-C interrupts them
+// instead of terminating the process. The previous SIGINT action is restored
+// when the last guard is dropped.
+struct InterruptGuard;
+
 // ===== impl GrpcClient =====
 
 impl GrpcClient {
-    pub fn connect(dest: &'static str) -> Result<Self, StdError> {
+    pub fn connect(
+        dest: &str,
+        options: &ConnectOptions,
+    ) -> Result<Self, StdError> {
+        let interceptor = AuthInterceptor::new(&options.credentials)?;
+        let username = options.credentials.username.clone();
+
+        // Unix domain sockets are reached through a custom connector, so the
+        // endpoint URI is just a placeholder in that case.
+        let mut client = match dest.strip_prefix("unix://") {
+            Some(path) => {
+                let endpoint = Endpoint::from_static("http://[::]:50051");
+                Self::connect_endpoint(
+                    endpoint,
+                    Some(path.to_owned()),
+                    interceptor,
+                    username,
+                )?
+            }
+            None => {
+                let mut endpoint = Endpoint::from_shared(dest.to_owned())?;
+                // HTTPS addresses always use TLS, even when no TLS option is
+                // given.
+                let https = endpoint.uri().scheme_str() == Some("https");
+                let tls = options
+                    .tls
+                    .clone()
+                    .or_else(|| https.then(TlsOptions::default));
+                if let Some(tls) = &tls {
+                    endpoint = endpoint.tls_config(tls.client_config()?)?;
+                }
+                Self::connect_endpoint(endpoint, None, interceptor, username)?
+            }
+        };
+        client.set_timeout(options.timeout);
+        Ok(client)
+    }
+
+    fn connect_endpoint(
+        endpoint: Endpoint,
+        unix_socket: Option<String>,
+        interceptor: AuthInterceptor,
+        username: Option<String>,
+    ) -> Result<Self, StdError> {
         // Initialize tokio runtime.
         let runtime = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -43,36 +205,163 @@ impl GrpcClient {
             .expect("Failed to obtain a new runtime object");
 
         // Connect to holod.
-        let client = runtime
-            .block_on(NorthboundClient::connect(dest))?
-            .max_encoding_message_size(usize::MAX)
-            .max_decoding_message_size(usize::MAX);
+        let client = runtime.block_on(new_client(
+            &endpoint,
+            unix_socket.as_deref(),
+            &interceptor,
+        ))?;
 
-        Ok(GrpcClient { client, runtime })
+        Ok(GrpcClient {
+            client,
+            connected: true,
+            reconnected: false,
+            timeout: None,
+            capabilities: None,
+            cache_dir: None,
+            cache_holo_modules: false,
+            prefetched: Default::default(),
+            interceptor,
+            username,
+            endpoint,
+            unix_socket,
+            runtime,
+        })
+    }
+
+    // Returns whether the connection to holod is currently up.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    // Returns whether the connection to holod was re-established since the
+    // last call, clearing the flag.
+    pub fn take_reconnected(&mut self) -> bool {
+        std::mem::take(&mut self.reconnected)
+    }
+
+    // Returns the capabilities advertised by holod.
+    pub fn capabilities(&self) -> Option<&proto::CapabilitiesResponse> {
+        self.capabilities.as_ref()
+    }
+
+    // Returns the YANG modules cache directory of the daemon version, unless
+    // the version is unknown.
+    pub fn modules_cache_dir(&self) -> Option<&str> {
+        self.cache_dir
+            .as_deref()
+            .filter(|_| self.cache_holo_modules)
+    }
+
+    // Returns the encoding used to exchange data with holod.
+    //
+    // LYB is preferred for efficiency, but only when advertised by holod.
+    // Daemons that don't advertise any encoding are assumed to support all of
+    // them.
+    pub fn data_format(&self) -> DataFormat {
+        let Some(capabilities) = &self.capabilities else {
+            return DataFormat::LYB;
+        };
+        let encodings = &capabilities.supported_encodings;
+        if encodings.is_empty()
+            || encodings.contains(&(proto::Encoding::Lyb as i32))
+        {
+            DataFormat::LYB
+        } else if encodings.contains(&(proto::Encoding::Json as i32)) {
+            DataFormat::JSON
+        } else {
+            DataFormat::XML
+        }
+    }
+
+    // Sets the maximum amount of time to wait for the response to a request.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    // Tries to re-establish the connection to holod, backing off
+    // exponentially between attempts.
+    fn reconnect(&mut self) -> Result<(), Error> {
+        let mut delay = RECONNECT_BACKOFF;
+        for _ in 0..RECONNECT_ATTEMPTS {
+            block_on_interruptible(&self.runtime, tokio::time::sleep(delay))
+                .ok_or(Error::Interrupted)?;
+            if let Ok(client) = self.runtime.block_on(new_client(
+                &self.endpoint,
+                self.unix_socket.as_deref(),
+                &self.interceptor,
+            )) {
+                self.client = client;
+                self.connected = true;
+                self.reconnected = true;
+                return Ok(());
+            }
+            delay *= 2;
+        }
+
+        Err(Error::Backend(tonic::Status::unavailable(
+            "connection to holod lost",
+        )))
     }
 
     pub fn load_modules(
         &mut self,
-        dest: &'static str,
         yang_ctx: &mut yang4::context::Context,
-    ) {
+    ) -> Result<(), String> {
         // Retrieve the set of capabilities supported by the daemon.
         let capabilities = self
             .rpc_sync_capabilities()
-            .expect("Failed to parse gRPC Capabilities() response");
+            .map_err(|error| {
+                format!("failed to fetch capabilities: {}", error)
+            })?
+            .into_inner();
+        self.capabilities = Some(capabilities.clone());
 
         // Establish a separate connection to holod for libyang to fetch any
         // missing YANG modules or submodules using the `GetSchema` RPC.
-        let client = Self::connect(dest).expect("Connection to holod failed");
+        let mut client = Self::connect_endpoint(
+            self.endpoint.clone(),
+            self.unix_socket.clone(),
+            self.interceptor.clone(),
+            self.username.clone(),
+        )
+        .map_err(|error| format!("connection to holod failed: {}", error))?;
+        client.set_timeout(self.timeout);
+
+        // Cache the fetched modules in a directory specific to the daemon
+        // version, since the same module revision (e.g. Holo augmentation and
+        // deviation modules) may differ across versions. When the version is
+        // unknown, Holo modules aren't cached at all.
+        let version = match capabilities.version.as_str() {
+            "" => CACHE_UNVERSIONED,
+            version => version,
+        };
+        match build_cache_dir(version) {
+            Ok(cache_dir) => {
+                client.cache_dir = Some(cache_dir);
+                client.cache_holo_modules = !capabilities.version.is_empty();
+            }
+            Err(error) => {
+                eprintln!("Failed to create YANG modules directory: {}", error)
+            }
+        }
+
+        // Fetch all missing modules concurrently up front, rather than one by
+        // one as libyang loads them.
+        client.prefetch_modules(&capabilities.supported_modules);
+
+        let cache_dir = client.cache_dir.clone();
+        self.cache_dir = cache_dir.clone();
+        self.cache_holo_modules = client.cache_holo_modules;
+        let client = Box::into_raw(Box::new(client));
         unsafe {
-            yang_ctx.set_module_import_callback(
-                ly_module_import_cb,
-                Box::into_raw(Box::new(client)) as _,
-            )
+            yang_ctx
+                .set_module_import_callback(ly_module_import_cb, client as _)
         };
 
         // Load YANG modules dynamically.
-        for module in capabilities.into_inner().supported_modules {
+        let mut result = Ok(());
+        let capabilities_modules = capabilities.supported_modules;
+        for module in &capabilities_modules {
             let revision = if module.revision.is_empty() {
                 None
             } else {
@@ -86,11 +375,112 @@ impl GrpcClient {
             if let Err(error) =
                 yang_ctx.load_module(&module.name, revision, features)
             {
-                panic!(
+                result = Err(format!(
                     "failed to load YANG module ({}): {}",
                     module.name, error
+                ));
+                break;
+            }
+        }
+
+        // Free the prefetched modules that libyang didn't ask for.
+        unsafe { (*client).prefetched.clear() };
+
+        // Save the module set for offline use.
+        if result.is_ok()
+            && let Some(cache_dir) = cache_dir
+        {
+            let modules = ModuleList::from(&capabilities_modules);
+            if let Err(error) = modules.save(&cache_dir) {
+                eprintln!("Failed to save YANG module list: {}", error);
+            }
+        }
+
+        result
+    }
+
+    // Returns the file path for caching a YANG module or submodule, unless it
+    // can't be cached.
+    fn cache_path(
+        &self,
+        module_name: &str,
+        name: &str,
+        revision: Option<&str>,
+    ) -> Option<String> {
+        let cache_dir = self.cache_dir.as_deref()?;
+
+        // Holo modules may change without a corresponding revision update.
+        if !self.cache_holo_modules && module_name.starts_with("holo") {
+            return None;
+        }
+
+        Some(build_cache_path(cache_dir, name, revision))
+    }
+
+    // Fetches the given modules concurrently, unless they're already cached.
+    fn prefetch_modules(&mut self, modules: &[proto::ModuleData]) {
+        let _guard = self.runtime.enter();
+        let mut requests = tokio::task::JoinSet::new();
+        for module in modules {
+            let revision = (!module.revision.is_empty())
+                .then_some(module.revision.as_str());
+            if let Some(path) =
+                self.cache_path(&module.name, &module.name, revision)
+                && cache_read(&path).is_some()
+            {
+                continue;
+            }
+
+            let mut client = self.client.clone();
+            let request = proto::GetSchemaRequest {
+                module_name: module.name.clone(),
+                module_revision: module.revision.clone(),
+                submodule_name: String::new(),
+                submodule_revision: String::new(),
+                format: proto::SchemaFormat::Yang.into(),
+            };
+            requests.spawn(async move {
+                let module_name = request.module_name.clone();
+                let module_revision = request.module_revision.clone();
+                let response = client.get_schema(request).await;
+                (module_name, module_revision, response)
+            });
+        }
+
+        // Errors are ignored here, as the modules are fetched again when
+        // libyang asks for them. The same goes when the timeout expires or the
+        // user presses Ctrl-C, which aborts the pending requests.
+        let timeout = self.timeout;
+        let responses = block_on_interruptible(&self.runtime, async move {
+            match timeout {
+                Some(timeout) => {
+                    tokio::time::timeout(timeout, requests.join_all())
+                        .await
+                        .ok()
+                }
+                None => Some(requests.join_all().await),
+            }
+        })
+        .flatten();
+        for (name, revision, response) in responses.into_iter().flatten() {
+            let Ok(response) = response else {
+                continue;
+            };
+            let data = response.into_inner().data;
+            let path = self.cache_path(
+                &name,
+                &name,
+                (!revision.is_empty()).then_some(revision.as_str()),
+            );
+            if let Some(path) = path
+                && let Err(error) = cache_write(&path, &data)
+            {
+                eprintln!(
+                    "Failed to save YANG module in the cache ({}): {}",
+                    name, error
                 );
             }
+            self.prefetched.insert(name, (revision, data));
         }
     }
 
@@ -107,23 +497,22 @@ impl GrpcClient {
                 encoding: proto::Encoding::from(format) as i32,
                 with_defaults,
                 path: xpath.unwrap_or_default(),
-            })
-            .map_err(Error::Backend)?
+            })?
             .into_inner()
-            .data
-            .unwrap();
-        Ok(data.data.unwrap())
+            .data;
+        data.and_then(|data| data.data)
+            .ok_or(Error::InvalidResponse("missing data tree"))
     }
 
     pub fn validate_candidate(
         &mut self,
         candidate: &DataTree<'static>,
     ) -> Result<(), Error> {
-        let config = proto::DataTree::new(DataFormat::LYB, candidate);
+        let config = proto::DataTree::new(self.data_format(), candidate);
         self.rpc_sync_validate(proto::ValidateRequest {
             config: Some(config),
         })
-        .map_err(Error::Backend)?;
+        .map_err(validation_error)?;
 
         Ok(())
     }
@@ -135,17 +524,25 @@ impl GrpcClient {
         comment: Option<String>,
     ) -> Result<(), Error> {
         let operation = proto::commit_request::Operation::Change as i32;
+        // Identify the operator in the commit comment.
+        let comment = match (&self.username, comment) {
+            (Some(username), Some(comment)) => {
+                format!("[{}] {}", username, comment)
+            }
+            (Some(username), None) => format!("[{}]", username),
+            (None, comment) => comment.unwrap_or_default(),
+        };
         let diff = running
             .diff(candidate, DataDiffFlags::DEFAULTS)
-            .expect("Failed to compare configurations");
-        let config = proto::DataTree::new(DataFormat::LYB, &diff);
+            .map_err(Error::ValidateConfig)?;
+        let config = proto::DataTree::new(self.data_format(), &diff);
         self.rpc_sync_commit(proto::CommitRequest {
             operation,
             config: Some(config),
-            comment: comment.unwrap_or_default(),
+            comment,
             confirmed_timeout: 0,
         })
-        .map_err(Error::Backend)?;
+        .map_err(validation_error)?;
 
         Ok(())
     }
@@ -156,72 +553,316 @@ impl GrpcClient {
     ) -> Result<proto::data_tree::Data, Error> {
         let data = self
             .rpc_sync_execute(proto::ExecuteRequest {
-                data: Some(proto::DataTree::new(DataFormat::LYB, &data)),
-            })
-            .map_err(Error::Backend)?
+                data: Some(proto::DataTree::new(self.data_format(), &data)),
+            })?
             .into_inner()
-            .data
-            .unwrap();
-        Ok(data.data.unwrap())
+            .data;
+        data.and_then(|data| data.data)
+            .ok_or(Error::InvalidResponse("missing data tree"))
     }
 
-    fn rpc_sync_capabilities(
+    // Returns the ID of the last transaction recorded in the rollback log, if
+    // any.
+    pub fn last_transaction(&mut self) -> Result<Option<u32>, Error> {
+        let transactions = self
+            .rpc_sync_list_transactions(proto::ListTransactionsRequest {})?
+            .into_inner();
+        Ok(transactions.iter().map(|transaction| transaction.id).max())
+    }
+
+    // Sends a request to holod and waits for its response.
+    //
+    // If the connection to holod was lost, try to reconnect and send the
+    // request again.
+    fn rpc_sync<Req, Resp, F, Fut>(
         &mut self,
-    ) -> Result<tonic::Response<proto::CapabilitiesResponse>, tonic::Status>
+        request: Req,
+        rpc: F,
+    ) -> Result<tonic::Response<Resp>, Error>
+    where
+        Req: Clone,
+        F: Fn(Client, tonic::Request<Req>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<Resp>, tonic::Status>>,
     {
-        let request = tonic::Request::new(proto::CapabilitiesRequest {});
-        self.runtime.block_on(self.client.capabilities(request))
+        match self.rpc_sync_once(request.clone(), &rpc) {
+            Err(Error::Backend(status))
+                if status.code() == tonic::Code::Unavailable =>
+            {
+                self.reconnect()?;
+                self.rpc_sync_once(request, &rpc)
+            }
+            result => result,
+        }
+    }
+
+    // Sends a single request to holod, giving up when the configured timeout
+    // expires or when the user presses Ctrl-C.
+    fn rpc_sync_once<Req, Resp, F, Fut>(
+        &mut self,
+        request: Req,
+        rpc: &F,
+    ) -> Result<tonic::Response<Resp>, Error>
+    where
+        F: Fn(Client, tonic::Request<Req>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<Resp>, tonic::Status>>,
+    {
+        let timeout = self.timeout;
+        let response = rpc(self.client.clone(), tonic::Request::new(request));
+        let result = block_on_interruptible(&self.runtime, async move {
+            match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, response)
+                    .await
+                    .map_err(|_| Error::Timeout)?,
+                None => response.await,
+            }
+            .map_err(Error::Backend)
+        })
+        .unwrap_or(Err(Error::Interrupted));
+
+        // Keep track of the connection state. The channel might recover on
+        // its own after a failed reconnection attempt.
+        match &result {
+            Err(Error::Backend(status))
+                if status.code() == tonic::Code::Unavailable =>
+            {
+                self.connected = false;
+            }
+            Ok(_) if !self.connected => {
+                self.connected = true;
+                self.reconnected = true;
+            }
+            _ => (),
+        }
+
+        result
+    }
+
+    fn rpc_sync_capabilities(
+        &mut self,
+    ) -> Result<tonic::Response<proto::CapabilitiesResponse>, Error> {
+        let request = proto::CapabilitiesRequest {};
+        self.rpc_sync(request, |mut client, request| async move {
+            client.capabilities(request).await
+        })
     }
 
     fn rpc_sync_get_schema(
         &mut self,
         request: proto::GetSchemaRequest,
-    ) -> Result<tonic::Response<proto::GetSchemaResponse>, tonic::Status> {
-        let request = tonic::Request::new(request);
-        self.runtime.block_on(self.client.get_schema(request))
+    ) -> Result<tonic::Response<proto::GetSchemaResponse>, Error> {
+        self.rpc_sync(request, |mut client, request| async move {
+            client.get_schema(request).await
+        })
     }
 
     fn rpc_sync_get(
         &mut self,
         request: proto::GetRequest,
-    ) -> Result<tonic::Response<proto::GetResponse>, tonic::Status> {
-        let request = tonic::Request::new(request);
-        self.runtime.block_on(self.client.get(request))
+    ) -> Result<tonic::Response<proto::GetResponse>, Error> {
+        self.rpc_sync(request, |mut client, request| async move {
+            client.get(request).await
+        })
     }
 
+    // Commits aren't idempotent, so they're never sent again after the
+    // connection is lost, as holod might have applied them already.
     fn rpc_sync_commit(
         &mut self,
         request: proto::CommitRequest,
-    ) -> Result<tonic::Response<proto::CommitResponse>, tonic::Status> {
-        let request = tonic::Request::new(request);
-        self.runtime.block_on(self.client.commit(request))
+    ) -> Result<tonic::Response<proto::CommitResponse>, Error> {
+        let rpc = |mut client: Client, request| async move {
+            client.commit(request).await
+        };
+        self.rpc_sync_once(request, &rpc).map_err(|error| {
+            connection_lost_error(
+                error,
+                "connection to holod lost, the commit might have been \
+                 applied: check the running configuration",
+            )
+        })
     }
 
     fn rpc_sync_validate(
         &mut self,
         request: proto::ValidateRequest,
-    ) -> Result<tonic::Response<proto::ValidateResponse>, tonic::Status> {
-        let request = tonic::Request::new(request);
-        self.runtime.block_on(self.client.validate(request))
+    ) -> Result<tonic::Response<proto::ValidateResponse>, Error> {
+        self.rpc_sync(request, |mut client, request| async move {
+            client.validate(request).await
+        })
+    }
+
+    fn rpc_sync_list_transactions(
+        &mut self,
+        request: proto::ListTransactionsRequest,
+    ) -> Result<tonic::Response<Vec<proto::ListTransactionsResponse>>, Error>
+    {
+        self.rpc_sync(request, |mut client, request| async move {
+            let mut stream =
+                client.list_transactions(request).await?.into_inner();
+            let mut transactions = vec![];
+            while let Some(transaction) = stream.message().await? {
+                transactions.push(transaction);
+            }
+            Ok(tonic::Response::new(transactions))
+        })
     }
 
+    // Like commits, YANG RPCs and actions (e.g. clearing a BGP session) are
+    // never sent again after the connection is lost.
     fn rpc_sync_execute(
         &mut self,
         request: proto::ExecuteRequest,
-    ) -> Result<tonic::Response<proto::ExecuteResponse>, tonic::Status> {
-        let request = tonic::Request::new(request);
-        self.runtime.block_on(self.client.execute(request))
+    ) -> Result<tonic::Response<proto::ExecuteResponse>, Error> {
+        let rpc = |mut client: Client, request| async move {
+            client.execute(request).await
+        };
+        self.rpc_sync_once(request, &rpc).map_err(|error| {
+            connection_lost_error(
+                error,
+                "connection to holod lost, the operation might have been \
+                 applied",
+            )
+        })
+    }
+}
+
+// ===== impl AuthInterceptor =====
+
+impl AuthInterceptor {
+    fn new(credentials: &Credentials) -> Result<Self, StdError> {
+        let authorization = credentials
+            .token
+            .as_ref()
+            .map(|token| format!("Bearer {}", token).parse())
+            .transpose()?;
+        let username = credentials.username.as_deref().map(username_metadata);
+        Ok(AuthInterceptor {
+            authorization,
+            username,
+        })
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(
+        &mut self,
+        mut request: tonic::Request<()>,
+    ) -> Result<tonic::Request<()>, tonic::Status> {
+        let metadata = request.metadata_mut();
+        if let Some(authorization) = &self.authorization {
+            metadata.insert("authorization", authorization.clone());
+        }
+        if let Some(username) = &self.username {
+            metadata.insert("username", username.clone());
+        }
+        Ok(request)
+    }
+}
+
+// ===== impl ModuleList =====
+
+impl ModuleList {
+    // Loads the module list saved in the given cache directory.
+    pub fn load(cache_dir: &str) -> Result<ModuleList, String> {
+        let path = format!("{}/{}", cache_dir, CACHE_MODULE_LIST);
+        let data = std::fs::read_to_string(&path).map_err(|error| {
+            format!("failed to read module list ({}): {}", path, error)
+        })?;
+        toml::from_str(&data).map_err(|error| {
+            format!("failed to parse module list ({}): {}", path, error)
+        })
+    }
+
+    // Saves the module list in the given cache directory.
+    fn save(&self, cache_dir: &str) -> Result<(), String> {
+        let path = format!("{}/{}", cache_dir, CACHE_MODULE_LIST);
+        let data = toml::to_string(self).map_err(|error| error.to_string())?;
+        cache_write_file(&path, &data).map_err(|error| error.to_string())
+    }
+}
+
+impl From<&Vec<proto::ModuleData>> for ModuleList {
+    fn from(modules: &Vec<proto::ModuleData>) -> ModuleList {
+        let modules = modules
+            .iter()
+            .map(|module| ModuleListEntry {
+                name: module.name.clone(),
+                revision: (!module.revision.is_empty())
+                    .then(|| module.revision.clone()),
+                features: module.supported_features.clone(),
+            })
+            .collect();
+        ModuleList { modules }
+    }
+}
+
+// ===== impl InterruptGuard =====
+
+impl InterruptGuard {
+    fn new() -> Self {
+        let mut state = INTERRUPT_STATE.lock().unwrap();
+        if state.0 == 0 {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction =
+                    sigint_handler as *const () as libc::sighandler_t;
+                libc::sigemptyset(&mut action.sa_mask);
+                let mut previous: libc::sigaction = std::mem::zeroed();
+                if libc::sigaction(libc::SIGINT, &action, &mut previous) == 0 {
+                    state.1 = Some(previous);
+                }
+            }
+        }
+        state.0 += 1;
+        InterruptGuard
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        let mut state = INTERRUPT_STATE.lock().unwrap();
+        state.0 -= 1;
+        if state.0 == 0
+            && let Some(previous) = state.1.take()
+        {
+            unsafe {
+                libc::sigaction(libc::SIGINT, &previous, std::ptr::null_mut())
+            };
+        }
+    }
+}
+
+// ===== impl TlsOptions =====
+
+impl TlsOptions {
+    // Builds the TLS configuration, trusting the system root certificates in
+    // addition to the given CA certificate.
+    fn client_config(&self) -> Result<ClientTlsConfig, std::io::Error> {
+        let mut config = ClientTlsConfig::new().with_enabled_roots();
+        if let Some(ca_cert) = &self.ca_cert {
+            let pem = std::fs::read(ca_cert)?;
+            config = config.ca_certificate(Certificate::from_pem(pem));
+        }
+        if let (Some(cert), Some(key)) = (&self.client_cert, &self.client_key) {
+            let cert = std::fs::read(cert)?;
+            let key = std::fs::read(key)?;
+            config = config.identity(Identity::from_pem(cert, key));
+        }
+        if let Some(domain) = &self.domain {
+            config = config.domain_name(domain);
+        }
+        Ok(config)
     }
 }
 
 // ===== impl proto::data_tree::Data =====
 
 impl proto::data_tree::Data {
-    pub fn as_bytes(&self) -> Option<&::prost::alloc::vec::Vec<u8>> {
-        if let proto::data_tree::Data::DataBytes(b) = &self {
-            Some(b)
-        } else {
-            None
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            proto::data_tree::Data::DataString(s) => s.as_bytes(),
+            proto::data_tree::Data::DataBytes(b) => b,
         }
     }
 }
@@ -266,6 +907,94 @@ impl From<DataFormat> for proto::Encoding {
 
 // ===== helper functions =====
 
+// Converts validation errors reported by holod into structured errors.
+fn validation_error(error: Error) -> Error {
+    match error {
+        Error::Backend(status)
+            if status.code() == tonic::Code::InvalidArgument =>
+        {
+            Error::Validation(ValidationError::from_status(&status))
+        }
+        error => error,
+    }
+}
+
+// Replaces the error returned when the connection to holod is lost during a
+// request that isn't retried.
+fn connection_lost_error(error: Error, message: &'static str) -> Error {
+    match error {
+        Error::Backend(status) if status.code() == tonic::Code::Unavailable => {
+            Error::Backend(tonic::Status::unavailable(message))
+        }
+        error => error,
+    }
+}
+
+// Converts the username into a metadata value, percent-encoding spaces,
+// percent signs, and non-ASCII or control characters so that it can always be
+// decoded back.
+fn username_metadata(username: &str) -> AsciiMetadataValue {
+    let mut encoded = String::new();
+    for byte in username.bytes() {
+        if byte.is_ascii_graphic() && byte != b'%' {
+            encoded.push(byte as char);
+        } else {
+            encoded += &format!("%{:02X}", byte);
+        }
+    }
+    MetadataValue::try_from(encoded).unwrap()
+}
+
+// Establishes a new connection to holod.
+async fn new_client(
+    endpoint: &Endpoint,
+    unix_socket: Option<&str>,
+    interceptor: &AuthInterceptor,
+) -> Result<Client, tonic::transport::Error> {
+    let channel = match unix_socket {
+        Some(path) => {
+            let path = path.to_owned();
+            let connector = service_fn(move |_: Uri| {
+                let path = path.clone();
+                async move {
+                    let stream = UnixStream::connect(path).await?;
+                    Ok::<_, std::io::Error>(TokioIo::new(stream))
+                }
+            });
+            endpoint.connect_with_connector(connector).await?
+        }
+        None => endpoint.connect().await?,
+    };
+    let client =
+        NorthboundClient::with_interceptor(channel, interceptor.clone())
+            .max_encoding_message_size(usize::MAX)
+            .max_decoding_message_size(usize::MAX);
+    Ok(client)
+}
+
+// Runs the given future to completion, unless the user presses Ctrl-C first.
+fn block_on_interruptible<F: Future>(
+    runtime: &tokio::runtime::Runtime,
+    future: F,
+) -> Option<F::Output> {
+    let _guard = InterruptGuard::new();
+    runtime.block_on(async move {
+        let interrupted = async {
+            while !INTERRUPTED.load(Ordering::SeqCst) {
+                tokio::time::sleep(INTERRUPT_POLL_INTERVAL).await;
+            }
+        };
+        tokio::select! {
+            output = future => Some(output),
+            _ = interrupted => None,
+        }
+    })
+}
+
+extern "C" fn sigint_handler(_signal: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
 unsafe extern "C" fn ly_module_import_cb(
     module_name: *const c_char,
     module_revision: *const c_char,
@@ -281,69 +1010,164 @@ unsafe extern "C" fn ly_module_import_cb(
     let submodule_name = char_ptr_to_opt_string(submodule_name);
     let submodule_revision = char_ptr_to_opt_string(submodule_revision);
 
-    // Retrive module or submodule via gRPC.
     let client = unsafe { &mut *(user_data as *mut GrpcClient) };
-    if let Ok(response) = client.rpc_sync_get_schema(proto::GetSchemaRequest {
-        module_name: module_name.clone(),
-        module_revision: module_revision.clone().unwrap_or_default(),
-        submodule_name: submodule_name.clone().unwrap_or_default(),
-        submodule_revision: submodule_revision.clone().unwrap_or_default(),
-        format: proto::SchemaFormat::Yang.into(),
-    }) {
-        let data = response.into_inner().data;
-
-        // Cache the module in the filesystem.
-        //
-        // Exclude Holo augmentation and deviation modules from caching, as they
-        // may change without corresponding version updates.
-        if !module_name.starts_with("holo") {
-            let path =
-                match (module_revision, submodule_name, submodule_revision) {
-                    (None, None, _) => build_cache_path(&module_name, None),
-                    (Some(module_revision), None, _) => {
-                        build_cache_path(&module_name, Some(&module_revision))
-                    }
-                    (_, Some(submodule_name), None) => {
-                        build_cache_path(&submodule_name, None)
-                    }
-                    (_, Some(submodule_name), Some(submodule_revision)) => {
-                        build_cache_path(
-                            &submodule_name,
-                            Some(&submodule_revision),
-                        )
-                    }
-                };
-            if let Err(error) = std::fs::write(&path, &data) {
+    let path = match (&module_revision, &submodule_name, &submodule_revision) {
+        (None, None, _) => client.cache_path(&module_name, &module_name, None),
+        (Some(module_revision), None, _) => {
+            client.cache_path(&module_name, &module_name, Some(module_revision))
+        }
+        (_, Some(submodule_name), None) => {
+            client.cache_path(&module_name, submodule_name, None)
+        }
+        (_, Some(submodule_name), Some(submodule_revision)) => client
+            .cache_path(&module_name, submodule_name, Some(submodule_revision)),
+    };
+
+    // Use the prefetched module if its revision matches.
+    let prefetched =
+        match (&submodule_name, client.prefetched.get(&module_name)) {
+            (None, Some((revision, _)))
+                if module_revision
+                    .as_ref()
+                    .is_none_or(|rev| rev == revision) =>
+            {
+                client.prefetched.remove(&module_name).map(|(_, data)| data)
+            }
+            _ => None,
+        };
+
+    // Otherwise, use the cached module or submodule, unless it's missing or
+    // corrupted.
+    let data = match prefetched.or_else(|| path.as_deref().and_then(cache_read))
+    {
+        Some(data) => data,
+        None => {
+            // Retrive module or submodule via gRPC.
+            let Ok(response) =
+                client.rpc_sync_get_schema(proto::GetSchemaRequest {
+                    module_name: module_name.clone(),
+                    module_revision: module_revision.unwrap_or_default(),
+                    submodule_name: submodule_name.unwrap_or_default(),
+                    submodule_revision: submodule_revision.unwrap_or_default(),
+                    format: proto::SchemaFormat::Yang.into(),
+                })
+            else {
+                return ffi::LY_ERR::LY_ENOTFOUND;
+            };
+            let data = response.into_inner().data;
+
+            // Cache the module in the filesystem.
+            if let Some(path) = &path
+                && let Err(error) = cache_write(path, &data)
+            {
                 eprintln!(
                     "Failed to save YANG module in the cache ({}): {}",
                     module_name, error
                 );
             }
-        }
 
-        // Return the retrieved module or submodule.
-        let data = CString::new(data).unwrap();
-        unsafe {
-            *format = ffi::LYS_INFORMAT::LYS_IN_YANG;
-            *module_data = data.as_ptr();
+            data
         }
-        std::mem::forget(data);
-        return ffi::LY_ERR::LY_SUCCESS;
+    };
+
+    // Return the retrieved module or submodule.
+    let data = CString::new(data).unwrap();
+    unsafe {
+        *format = ffi::LYS_INFORMAT::LYS_IN_YANG;
+        *module_data = data.as_ptr();
+    }
+    std::mem::forget(data);
+    ffi::LY_ERR::LY_SUCCESS
+}
+
+// Builds the directory path for caching the YANG modules of the given daemon
+// version.
+fn build_cache_dir(version: &str) -> Result<String, std::io::Error> {
+    let version = version
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "._-".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let cache_dir = format!("{}/{}", YANG_CACHE_DIR.get().unwrap(), version);
+
+    // Create the directory, marking it as a cache directory.
+    std::fs::create_dir_all(&cache_dir)?;
+    let sentinel = format!("{}/{}", cache_dir, CACHE_SENTINEL);
+    if !std::path::Path::new(&sentinel).exists() {
+        std::fs::write(&sentinel, "")?;
     }
 
-    ffi::LY_ERR::LY_ENOTFOUND
+    Ok(cache_dir)
 }
 
 // Builds the file path for caching a YANG module or submodule.
-fn build_cache_path(name: &str, revision: Option<&str>) -> String {
+fn build_cache_path(
+    cache_dir: &str,
+    name: &str,
+    revision: Option<&str>,
+) -> String {
     match revision {
-        Some(revision) => {
-            format!("{}/{}@{}.yang", YANG_MODULES_DIR, name, revision)
-        }
-        None => format!("{}/{}.yang", YANG_MODULES_DIR, name),
+        Some(revision) => format!("{}/{}@{}.yang", cache_dir, name, revision),
+        None => format!("{}/{}.yang", cache_dir, name),
     }
 }
 
+// Reads a YANG module or submodule from the cache, checking its integrity
+// against the digest saved along with it.
+fn cache_read(path: &str) -> Option<String> {
+    let data = std::fs::read_to_string(path).ok()?;
+    let digest = std::fs::read_to_string(format!("{}.sha256", path)).ok()?;
+    (digest.trim() == content_digest(&data)).then_some(data)
+}
+
+// Saves a YANG module or submodule in the cache, along with its digest.
+//
+// The digest is saved first, so that a module is never picked up until both
+// are up to date.
+fn cache_write(path: &str, data: &str) -> Result<(), std::io::Error> {
+    // The cache might have been cleared in the meantime.
+    if let Some(dir) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    cache_write_file(&format!("{}.sha256", path), &content_digest(data))?;
+    cache_write_file(path, data)
+}
+
+// Writes a file of the cache through a temporary file, so that a partially
+// written file is never picked up.
+//
+// The temporary file name is unique, since several threads or processes might
+// save the same module at the same time.
+pub fn cache_write_file(path: &str, data: &str) -> Result<(), std::io::Error> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let tmp_path = format!(
+        "{}.{}.{}.tmp",
+        path,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    std::fs::write(&tmp_path, data)?;
+    std::fs::rename(&tmp_path, path).inspect_err(|_| {
+        let _ = std::fs::remove_file(&tmp_path);
+    })
+}
+
+// Computes the SHA-256 digest of a YANG module or submodule.
+fn content_digest(data: &str) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
 // Converts C String to owned string.
 fn char_ptr_to_string(c_str: *const c_char) -> String {
     unsafe { CStr::from_ptr(c_str).to_string_lossy().into_owned() }
@@ -357,3 +1181,106 @@ fn char_ptr_to_opt_string(c_str: *const c_char) -> Option<String> {
         Some(char_ptr_to_string(c_str))
     }
 }
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_write_concurrent() {
+        let dir = std::env::temp_dir()
+            .join(format!("holo-cli-test-cache-{}", std::process::id()));
+        let path = dir.join("module@2024-01-01.yang");
+        let path = path.to_str().unwrap();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| cache_write(path, "module test {}").unwrap());
+            }
+        });
+        assert_eq!(cache_read(path).as_deref(), Some("module test {}"));
+
+        // Corrupted modules aren't picked up.
+        std::fs::write(path, "module test { }").unwrap();
+        assert_eq!(cache_read(path), None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn module_list_round_trip() {
+        let dir = std::env::temp_dir()
+            .join(format!("holo-cli-test-modules-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.to_str().unwrap();
+
+        let modules = vec![
+            proto::ModuleData {
+                name: "ietf-routing".to_owned(),
+                revision: "2018-03-13".to_owned(),
+                supported_features: vec!["router-id".to_owned()],
+                ..Default::default()
+            },
+            proto::ModuleData {
+                name: "holo-routing".to_owned(),
+                ..Default::default()
+            },
+        ];
+        ModuleList::from(&modules).save(dir).unwrap();
+        let list = ModuleList::load(dir).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+
+        let list = list
+            .modules
+            .iter()
+            .map(|module| {
+                (
+                    module.name.as_str(),
+                    module.revision.as_deref(),
+                    &module.features,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            list,
+            [
+                (
+                    "ietf-routing",
+                    Some("2018-03-13"),
+                    &vec!["router-id".to_owned()]
+                ),
+                ("holo-routing", None, &vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn interrupt_restores_sigint_action() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let output = block_on_interruptible(&runtime, async {
+            unsafe { libc::raise(libc::SIGINT) };
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+        assert!(output.is_none());
+
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        unsafe { libc::sigaction(libc::SIGINT, std::ptr::null(), &mut action) };
+        assert_eq!(action.sa_sigaction, libc::SIG_DFL);
+    }
+
+    #[test]
+    fn username_metadata_encoding() {
+        assert_eq!(username_metadata("admin"), "admin");
+        assert_eq!(username_metadata("john doe"), "john%20doe");
+        assert_eq!(username_metadata("josé"), "jos%C3%A9");
+        assert_eq!(username_metadata("jos%C3%A9"), "jos%25C3%25A9");
+        assert_eq!(username_metadata("a%b"), "a%25b");
+        assert_eq!(username_metadata("a b"), "a%20b");
+        assert_eq!(username_metadata("a\tb%c"), "a%09b%25c");
+    }
+}
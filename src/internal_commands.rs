@@ -7,26 +7,30 @@
 use std::collections::BTreeMap;
 use std::fmt::Write;
 use std::process::{Child, Command, Stdio};
+use std::time::Duration;
 
 use chrono::prelude::*;
 use indextree::NodeId;
 use prettytable::{Table, format, row};
-use similar::TextDiff;
 use yang4::data::{
-    Data, DataFormat, DataNodeRef, DataOperation, DataParserFlags,
+    Data, DataDiffOp, DataFormat, DataNodeRef, DataOperation, DataParserFlags,
     DataPrinterFlags, DataTree, DataValidationFlags,
 };
 use yang4::schema::SchemaNodeKind;
 
-use crate::YANG_CTX;
-use crate::grpc::proto;
-use crate::parser::ParsedArgs;
-use crate::session::{CommandMode, ConfigurationType, Session};
+use crate::grpc::{
+    CACHE_COMMAND_INDEX_PREFIX, CACHE_MODULE_LIST, CACHE_SENTINEL, proto,
+};
+use crate::parser::{self, ParsedArgs};
+use crate::session::{CapturedOutput, CommandMode, ConfigurationType, Session};
 use crate::token::{Commands, TokenKind};
+use crate::{YANG_CACHE_DIR, YANG_CTX, token_yang};
 
 const XPATH_PROTOCOL: &str =
     "/ietf-routing:routing/control-plane-protocols/control-plane-protocol";
 const XPATH_RIB: &str = "/ietf-routing:routing/ribs/rib";
+// Number of nodes above which deletions must be confirmed.
+const DELETE_CONFIRM_THRESHOLD: usize = 5;
 
 struct YangTableBuilder<'a> {
     session: &'a mut Session,
@@ -176,12 +180,16 @@ impl<'a> YangTableBuilder<'a> {
                         match format {
                             YangValueDisplayFormat::Raw => value,
                             YangValueDisplayFormat::Hex16 => {
-                                let value = value.parse::<u32>().unwrap();
-                                format!("{:#06x}", value)
+                                match value.parse::<u32>() {
+                                    Ok(value) => format!("{:#06x}", value),
+                                    Err(_) => value,
+                                }
                             }
                             YangValueDisplayFormat::Hex32 => {
-                                let value = value.parse::<u32>().unwrap();
-                                format!("{:#010x}", value)
+                                match value.parse::<u32>() {
+                                    Ok(value) => format!("{:#010x}", value),
+                                    Err(_) => value,
+                                }
                             }
                         }
                     }
@@ -216,15 +224,30 @@ impl<'a> YangTableBuilder<'a> {
             .flat_map(|(_, columns)| columns.iter())
             .map(|column| column.title)
             .collect();
-        table.set_titles(column_titles.into());
+        table.set_titles(column_titles.clone().into());
 
         // Populate the table with data from the specified paths.
         let values = Vec::new();
         Self::show_path(&mut table, dnode, &self.paths, values);
 
+        // Keep the table as is when capturing the output, so that it can be
+        // merged with tables from other devices.
+        if let Some(capture) = self.session.capture_mut() {
+            let titles = column_titles.iter().map(|title| title.to_string());
+            let rows = table
+                .row_iter()
+                .map(|row| row.iter().map(|cell| cell.get_content()).collect());
+            capture.push(CapturedOutput::Table {
+                titles: titles.collect(),
+                rows: rows.collect(),
+            });
+            return Ok(());
+        }
+
         // Print the table to stdout.
         if let Err(error) = page_table(self.session, &table) {
-            println!("% failed to display data: {}", error);
+            self.session
+                .output_line(&format!("% failed to display data: {}", error));
         }
 
         Ok(())
@@ -233,8 +256,8 @@ impl<'a> YangTableBuilder<'a> {
 
 // ===== helper functions =====
 
-fn get_arg(args: &mut ParsedArgs, name: &str) -> String {
-    get_opt_arg(args, name).expect("Failed to find argument")
+fn get_arg(args: &mut ParsedArgs, name: &str) -> Result<String, String> {
+    get_opt_arg(args, name).ok_or(format!("missing argument: {}", name))
 }
 
 fn get_opt_arg(args: &mut ParsedArgs, name: &str) -> Option<String> {
@@ -246,6 +269,49 @@ fn get_opt_arg(args: &mut ParsedArgs, name: &str) -> Option<String> {
     None
 }
 
+// Percent-encodes all characters except the unreserved ones (RFC 3986).
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            write!(encoded, "%{:02X}", byte).unwrap();
+        }
+    }
+    encoded
+}
+
+// Formats a string as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut string = String::from('"');
+    for c in value.chars() {
+        match c {
+            '"' => string.push_str("\\\""),
+            '\\' => string.push_str("\\\\"),
+            c if c.is_control() => {
+                write!(string, "\\u{:04x}", c as u32).unwrap()
+            }
+            c => string.push(c),
+        }
+    }
+    string.push('"');
+    string
+}
+
+// Asks the user a question, returning the lowercase answer.
+//
+// An empty answer is returned when stdin is closed.
+fn prompt_answer(question: &str) -> String {
+    use std::io::Write;
+
+    print!("{} ", question);
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    let _ = std::io::stdin().read_line(&mut answer);
+    answer.trim().to_lowercase()
+}
+
 fn pager() -> Result<Child, std::io::Error> {
     Command::new("less")
         // Exit immediately if the data fits on one screen.
@@ -256,8 +322,13 @@ fn pager() -> Result<Child, std::io::Error> {
         .spawn()
 }
 
-fn page_output(session: &Session, data: &str) -> Result<(), std::io::Error> {
-    if session.use_pager() {
+fn page_output(
+    session: &mut Session,
+    data: &str,
+) -> Result<(), std::io::Error> {
+    if let Some(capture) = session.capture_mut() {
+        capture.push(CapturedOutput::Text(format!("{}\n", data)));
+    } else if session.use_pager() {
         use std::io::Write;
 
         // Spawn the pager process.
@@ -276,12 +347,20 @@ fn page_output(session: &Session, data: &str) -> Result<(), std::io::Error> {
     Ok(())
 }
 
-fn page_table(session: &Session, table: &Table) -> Result<(), std::io::Error> {
+fn page_table(
+    session: &mut Session,
+    table: &Table,
+) -> Result<(), std::io::Error> {
     if table.is_empty() {
         return Ok(());
     }
 
-    if session.use_pager() {
+    if let Some(capture) = session.capture_mut() {
+        let mut output = Vec::new();
+        table.print(&mut output)?;
+        let output = String::from_utf8_lossy(&output);
+        capture.push(CapturedOutput::Text(format!("{}\n", output)));
+    } else if session.use_pager() {
         use std::io::Write;
 
         // Spawn the pager process.
@@ -312,13 +391,13 @@ fn fetch_data(
     xpath: &str,
 ) -> Result<DataTree<'static>, String> {
     let yang_ctx = YANG_CTX.get().unwrap();
-    let data_format = DataFormat::LYB;
+    let data_format = session.data_format();
     let data = session
         .get(data_type, data_format, true, Some(xpath.to_owned()))
         .map_err(|error| format!("% failed to fetch state data: {}", error))?;
     DataTree::parse_string(
         yang_ctx,
-        data.as_bytes().unwrap(),
+        data.as_bytes(),
         data_format,
         DataParserFlags::NO_VALIDATION,
         DataValidationFlags::PRESENT,
@@ -344,7 +423,7 @@ impl DataNodeRefExt for DataNodeRef<'_> {
     fn child_opt_value(&self, name: &str) -> Option<String> {
         self.children()
             .find(|dnode| dnode.schema().name() == name)
-            .map(|dnode| dnode.value_canonical().unwrap())
+            .and_then(|dnode| dnode.value_canonical())
     }
 
     fn relative_value(&self, xpath: &str) -> String {
@@ -353,21 +432,106 @@ impl DataNodeRefExt for DataNodeRef<'_> {
 
     fn relative_opt_value(&self, xpath: &str) -> Option<String> {
         self.find_xpath(xpath)
-            .unwrap()
+            .ok()?
             .next()
-            .map(|dnode| dnode.value_canonical().unwrap())
+            .and_then(|dnode| dnode.value_canonical())
     }
 }
 
+// ===== "terminal timeout" =====
+
+pub fn cmd_terminal_timeout(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let seconds = get_arg(&mut args, "seconds")?;
+    let seconds = seconds
+        .parse::<u64>()
+        .map_err(|error| format!("invalid timeout: {}", error))?;
+    let timeout = (seconds != 0).then(|| Duration::from_secs(seconds));
+    session
+        .set_timeout(timeout)
+        .map_err(|error| error.to_string())?;
+    Ok(false)
+}
+
+pub fn cmd_terminal_commit_preview(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let state = get_arg(&mut args, "state")?;
+    session.commit_preview_set(state == "enable");
+    Ok(false)
+}
+
+// ===== "connect" =====
+
+pub fn cmd_connect(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let name = get_arg(&mut args, "name")?;
+    let address = get_arg(&mut args, "address")?;
+    session
+        .connect(&name, &address)
+        .map_err(|error| error.to_string())?;
+    Ok(false)
+}
+
+// ===== "switch" =====
+
+pub fn cmd_switch(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let name = get_arg(&mut args, "name")?;
+    session.switch(&name).map_err(|error| error.to_string())?;
+    Ok(false)
+}
+
 // ===== "configure" =====
 
 pub fn cmd_config(
     _commands: &Commands,
     session: &mut Session,
-    _args: ParsedArgs,
+    mut args: ParsedArgs,
 ) -> Result<bool, String> {
+    let exclusive = get_opt_arg(&mut args, "exclusive").is_some();
+
+    // holod doesn't provide a candidate datastore that sessions could share.
+    if get_opt_arg(&mut args, "type").as_deref() == Some("shared") {
+        return Err(
+            "shared candidate configurations aren't supported by holod"
+                .to_owned(),
+        );
+    }
+
     let mode = CommandMode::Configure { nodes: vec![] };
     session.mode_set(mode);
+    if exclusive {
+        session.exclusive_set();
+    }
+    session.candidate_autosave_start();
+
+    // Offer to resume the candidate configuration of a session that crashed.
+    if let Some(path) = session.candidate_autosaved() {
+        let answer = prompt_answer(
+            "Uncommitted changes from a previous session found, resume \
+             them? [yes/NO]",
+        );
+        if answer == "yes" || answer == "y" {
+            if let Err(error) = session.candidate_resume(&path) {
+                session.output_line(&format!("% {}", error));
+            }
+        } else {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+
     Ok(false)
 }
 
@@ -387,6 +551,12 @@ pub fn cmd_exit_config(
     session: &mut Session,
     _args: ParsedArgs,
 ) -> Result<bool, String> {
+    if session.mode().as_configure().unwrap().is_empty() {
+        if config_exit_check(session) {
+            session.mode_set(CommandMode::Operational);
+        }
+        return Ok(false);
+    }
     session.mode_config_exit();
     Ok(false)
 }
@@ -398,10 +568,38 @@ pub fn cmd_end(
     session: &mut Session,
     _args: ParsedArgs,
 ) -> Result<bool, String> {
-    session.mode_set(CommandMode::Operational);
+    if config_exit_check(session) {
+        session.mode_set(CommandMode::Operational);
+    }
     Ok(false)
 }
 
+// Checks for uncommitted changes in the candidate configuration before
+// leaving configuration mode, asking whether to commit or discard them.
+//
+// Non-interactive sessions (e.g. commands given as arguments or read from a
+// file) discard them, as nobody can answer.
+//
+// Returns whether configuration mode can be left.
+fn config_exit_check(session: &mut Session) -> bool {
+    if !session.candidate_changed() {
+        return true;
+    }
+    if !session.interactive() {
+        session.output_line("% uncommitted changes discarded");
+        return true;
+    }
+
+    let answer = prompt_answer(
+        "Uncommitted changes found, commit them? [yes/no/CANCEL]",
+    );
+    match answer.as_str() {
+        "yes" | "y" => commit(session, None),
+        "no" | "n" => true,
+        _ => false,
+    }
+}
+
 // ===== "list" =====
 
 pub fn cmd_list(
@@ -412,23 +610,28 @@ pub fn cmd_list(
     match session.mode() {
         CommandMode::Operational => {
             // List EXEC-level commands.
-            cmd_list_root(commands, &commands.exec_root);
+            cmd_list_root(commands, session, &commands.exec_root);
         }
         CommandMode::Configure { .. } => {
             // List internal configuration commands first.
-            cmd_list_root(commands, &commands.config_dflt_internal);
-            println!("---");
-            cmd_list_root(commands, &commands.config_root_internal);
-            println!("---");
+            cmd_list_root(commands, session, &commands.config_dflt_internal);
+            session.output_line("---");
+            cmd_list_root(commands, session, &commands.config_root_internal);
+            session.output_line("---");
             // List YANG configuration commands.
-            cmd_list_root(commands, &session.mode().token(commands));
+            let token_id = session.mode().token(commands);
+            cmd_list_root(commands, session, &token_id);
         }
     }
 
     Ok(false)
 }
 
-pub fn cmd_list_root(commands: &Commands, top_token_id: &NodeId) {
+pub fn cmd_list_root(
+    commands: &Commands,
+    session: &mut Session,
+    top_token_id: &NodeId,
+) {
     for token_id in
         top_token_id
             .descendants(&commands.arena)
@@ -454,7 +657,7 @@ pub fn cmd_list_root(commands: &Commands, top_token_id: &NodeId) {
             cmd_string.push(' ');
         }
 
-        println!("{}", cmd_string);
+        session.output_line(&cmd_string);
     }
 }
 
@@ -465,10 +668,49 @@ pub fn cmd_pwd(
     session: &mut Session,
     _args: ParsedArgs,
 ) -> Result<bool, String> {
-    println!(
-        "{}",
-        session.mode().data_path().unwrap_or_else(|| "/".to_owned())
-    );
+    let data_path =
+        session.mode().data_path().unwrap_or_else(|| "/".to_owned());
+    session.output_line(&data_path);
+    Ok(false)
+}
+
+// ===== "edit" =====
+
+pub fn cmd_edit(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let path = get_arg(&mut args, "path")?;
+    let words =
+        parser::split_words(&path).map_err(|error| error.to_string())?;
+    if let Err(error) = session.mode_config_edit(&words) {
+        session.output_line(&format!("% {}", error));
+    }
+    Ok(false)
+}
+
+// ===== "up" =====
+
+pub fn cmd_up(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let count = match get_opt_arg(&mut args, "count") {
+        Some(count) => match count.parse::<usize>() {
+            Ok(count) => count,
+            Err(_) => {
+                session.output_line(&format!(
+                    "% invalid number of levels: {}",
+                    count
+                ));
+                return Ok(false);
+            }
+        },
+        None => 1,
+    };
+    session.mode_config_up(count);
     Ok(false)
 }
 
@@ -483,6 +725,107 @@ pub fn cmd_top(
     Ok(false)
 }
 
+// ===== "copy" =====
+
+pub fn cmd_copy(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let path = get_arg(&mut args, "path")?;
+    let words =
+        parser::split_words(&path).map_err(|error| error.to_string())?;
+    if let Err(error) = session.candidate_copy(&words) {
+        session.output_line(&format!("% {}", error));
+    }
+    Ok(false)
+}
+
+// ===== "rename" =====
+
+pub fn cmd_rename(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let update_refs = get_opt_arg(&mut args, "update-references").is_some();
+    let path = get_arg(&mut args, "path")?;
+    let words =
+        parser::split_words(&path).map_err(|error| error.to_string())?;
+    match session.candidate_rename(&words, update_refs) {
+        Ok(refs) if update_refs => {
+            session.output_line(&format!("% {} reference(s) updated", refs))
+        }
+        Ok(_) => (),
+        Err(error) => session.output_line(&format!("% {}", error)),
+    }
+    Ok(false)
+}
+
+// ===== "insert" =====
+
+pub fn cmd_insert(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let path = get_arg(&mut args, "path")?;
+    let words =
+        parser::split_words(&path).map_err(|error| error.to_string())?;
+    if let Err(error) = session.candidate_insert(&words) {
+        session.output_line(&format!("% {}", error));
+    }
+    Ok(false)
+}
+
+// ===== "delete" =====
+
+pub fn cmd_delete(
+    _commands: &Commands,
+    session: &mut Session,
+    mut args: ParsedArgs,
+) -> Result<bool, String> {
+    let path = get_arg(&mut args, "path")?;
+    let words =
+        parser::split_words(&path).map_err(|error| error.to_string())?;
+    let paths = match session.candidate_find(&words) {
+        Ok(paths) => paths,
+        Err(error) => {
+            session.output_line(&format!("% {}", error));
+            return Ok(false);
+        }
+    };
+    if paths.is_empty() {
+        session.output_line("% nothing to delete");
+        return Ok(false);
+    }
+
+    // Show what will be removed, asking for confirmation when it's a lot.
+    for (path, count) in &paths {
+        session.output_line(&format!(
+            "- {} ({} node{})",
+            token_yang::data_path_to_cli_path(path),
+            count,
+            if *count == 1 { "" } else { "s" }
+        ));
+    }
+    let total: usize = paths.iter().map(|(_, count)| count).sum();
+    if total > DELETE_CONFIRM_THRESHOLD {
+        let answer =
+            prompt_answer(&format!("Delete these {} nodes? [yes/NO]", total));
+        if answer != "yes" && answer != "y" {
+            session.output_line("% delete aborted");
+            return Ok(false);
+        }
+    }
+
+    let paths = paths.into_iter().map(|(path, _)| path).collect::<Vec<_>>();
+    if let Err(error) = session.candidate_delete(&paths) {
+        session.output_line(&format!("% {}", error));
+    }
+    Ok(false)
+}
+
 // ===== "discard" =====
 
 pub fn cmd_discard(
@@ -497,21 +840,95 @@ pub fn cmd_discard(
 // ===== "commit" =====
 
 pub fn cmd_commit(
-    _commands: &Commands,
+    commands: &Commands,
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
+    let check = get_opt_arg(&mut args, "check").is_some();
+    let and_quit = get_opt_arg(&mut args, "and-quit").is_some();
     let comment = get_opt_arg(&mut args, "comment");
+
+    // Validate the candidate configuration without committing it.
+    if check {
+        return cmd_validate(commands, session, args);
+    }
+
+    if commit(session, comment) && and_quit {
+        session.mode_set(CommandMode::Operational);
+    }
+    Ok(false)
+}
+
+// Commits the candidate configuration, returning whether it succeeded.
+fn commit(session: &mut Session, comment: Option<String>) -> bool {
+    // Show the pending changes and ask for confirmation first, if enabled.
+    if session.commit_preview() {
+        let changes = match config_changes(session) {
+            Ok(changes) => changes,
+            Err(error) => {
+                session.output_line(&format!("% {}", error));
+                return false;
+            }
+        };
+        if changes.is_empty() {
+            session.output_line("% no changes to commit");
+            return true;
+        }
+        session.output(&changes);
+        let answer = prompt_answer("Commit these changes? [yes/NO]");
+        if answer != "yes" && answer != "y" {
+            session.output_line("% commit aborted");
+            return false;
+        }
+    }
+
+    // Check whether another session committed in the meantime, in which case
+    // committing would silently revert its changes.
+    match session.running_check() {
+        Ok(None) => (),
+        Ok(Some(_)) if session.exclusive() => {
+            session.output_line(
+                "% running configuration was changed by another session, \
+                 commit aborted",
+            );
+            return false;
+        }
+        Ok(Some(update)) => {
+            session.output_line(
+                "% running configuration was changed by another session",
+            );
+            let answer = prompt_answer(
+                "Rebase the candidate configuration on top of it? [yes/NO]",
+            );
+            if answer != "yes" && answer != "y" {
+                session.output_line("% commit aborted");
+                return false;
+            }
+            match session.candidate_rebase(update) {
+                Ok(()) => session.output_line(
+                    "% candidate configuration rebased, review the changes \
+                     and commit again",
+                ),
+                Err(error) => session.output_line(&format!("% {}", error)),
+            }
+            return false;
+        }
+        Err(error) => {
+            session.output_line(&format!("% {}", error));
+            return false;
+        }
+    }
+
     match session.candidate_commit(comment) {
         Ok(_) => {
-            println!("% configuration committed successfully");
+            session.output_line("% configuration committed successfully");
+            true
         }
         Err(error) => {
-            println!("% {}", error);
+            session.output_line(&format!("% {}", error));
+            false
         }
     }
-
-    Ok(false)
 }
 
 // ===== "validate" =====
@@ -522,10 +939,9 @@ pub fn cmd_validate(
     _args: ParsedArgs,
 ) -> Result<bool, String> {
     match session.candidate_validate() {
-        Ok(_) => println!("% candidate configuration validated successfully"),
-        Err(error) => {
-            println!("% {}", error)
-        }
+        Ok(_) => session
+            .output_line("% candidate configuration validated successfully"),
+        Err(error) => session.output_line(&format!("% {}", error)),
     }
 
     Ok(false)
@@ -536,26 +952,16 @@ pub fn cmd_validate(
 fn cmd_show_config_cmds(
     config: &DataTree<'static>,
     with_defaults: bool,
+    annotate: bool,
 ) -> String {
     let mut output = String::new();
 
     // Iterate over data nodes that represent full commands.
     for dnode in config
         .traverse()
-        .filter(|dnode| {
-            let snode = dnode.schema();
-            match snode.kind() {
-                SchemaNodeKind::Container => !snode.is_np_container(),
-                SchemaNodeKind::Leaf => !snode.is_list_key(),
-                SchemaNodeKind::LeafList => true,
-                SchemaNodeKind::List => true,
-                _ => false,
-            }
-        })
-        .filter(|dnode| with_defaults || !dnode.is_default())
+        .filter(is_config_cmd)
+        .filter(|dnode| with_defaults || annotate || !dnode.is_default())
     {
-        let mut tokens = vec![];
-
         // Indentation.
         let mut indent = String::new();
         for _ in dnode
@@ -566,33 +972,20 @@ fn cmd_show_config_cmds(
         }
 
         // Build command line.
-        for dnode in dnode
-            .inclusive_ancestors()
-            .take_while(|iter| {
-                if *iter == dnode {
-                    return true;
-                }
-                let snode = iter.schema();
-                snode.kind() != SchemaNodeKind::List
-            })
-            .collect::<Vec<DataNodeRef<'_>>>()
-            .iter()
-            .rev()
-        {
-            tokens.push(dnode.schema().name().to_owned());
-            for dnode in dnode.list_keys() {
-                tokens.push(dnode.value_canonical().unwrap());
-            }
-            if let Some(value) = dnode.value_canonical() {
-                tokens.push(value.clone());
-            }
-        }
+        let tokens = config_cmd_tokens(&dnode);
 
         // Print command.
         if dnode.schema().kind() == SchemaNodeKind::List {
             writeln!(output, "{}!", indent).unwrap();
         }
-        writeln!(output, "{}{}", indent, tokens.join(" ")).unwrap();
+        if annotate && let Some(description) = config_cmd_description(&dnode) {
+            writeln!(output, "{}# {}", indent, description).unwrap();
+        }
+        write!(output, "{}{}", indent, tokens.join(" ")).unwrap();
+        if annotate && let Some(annotation) = config_cmd_annotation(&dnode) {
+            write!(output, "  # {}", annotation).unwrap();
+        }
+        writeln!(output).unwrap();
     }
 
     // Footer.
@@ -601,6 +994,94 @@ fn cmd_show_config_cmds(
     output
 }
 
+// Returns whether the data node represents a full configuration command.
+fn is_config_cmd(dnode: &DataNodeRef<'_>) -> bool {
+    let snode = dnode.schema();
+    match snode.kind() {
+        SchemaNodeKind::Container => !snode.is_np_container(),
+        SchemaNodeKind::Leaf => !snode.is_list_key(),
+        SchemaNodeKind::LeafList => true,
+        SchemaNodeKind::List => true,
+        _ => false,
+    }
+}
+
+// Returns the first sentence of the description of a leaf or leaf-list, to be
+// shown above its command.
+fn config_cmd_description(dnode: &DataNodeRef<'_>) -> Option<String> {
+    let snode = dnode.schema();
+    if !matches!(
+        snode.kind(),
+        SchemaNodeKind::Leaf | SchemaNodeKind::LeafList
+    ) {
+        return None;
+    }
+
+    let description = snode
+        .description()?
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let summary = match description.find(". ") {
+        Some(pos) => description[..=pos].to_owned(),
+        None => description,
+    };
+    Some(summary)
+}
+
+// Returns the annotation of a leaf or leaf-list command, telling whether its
+// value is the default one (or which default it overrides) and its units.
+fn config_cmd_annotation(dnode: &DataNodeRef<'_>) -> Option<String> {
+    let snode = dnode.schema();
+    let mut annotations = vec![];
+
+    if dnode.is_default() {
+        annotations.push("default".to_owned());
+    } else if snode.has_default()
+        && let Ok(Some(default)) = snode.default_value_canonical()
+        && dnode
+            .value_canonical()
+            .is_some_and(|value| value != default)
+    {
+        annotations.push(format!("modified, default is {}", default));
+    }
+    if let Some(units) = snode.units() {
+        annotations.push(format!("units: {}", units));
+    }
+
+    (!annotations.is_empty()).then(|| annotations.join(", "))
+}
+
+// Builds the command line of a data node, relative to its closest list
+// ancestor.
+fn config_cmd_tokens(dnode: &DataNodeRef<'_>) -> Vec<String> {
+    let mut tokens = vec![];
+    for iter in dnode
+        .inclusive_ancestors()
+        .take_while(|iter| {
+            if iter == dnode {
+                return true;
+            }
+            let snode = iter.schema();
+            snode.kind() != SchemaNodeKind::List
+        })
+        .collect::<Vec<DataNodeRef<'_>>>()
+        .iter()
+        .rev()
+    {
+        tokens.push(iter.schema().name().to_owned());
+        for value in
+            iter.list_keys().filter_map(|dnode| dnode.value_canonical())
+        {
+            tokens.push(parser::quote_word(&value));
+        }
+        if let Some(value) = iter.value_canonical() {
+            tokens.push(parser::quote_word(&value));
+        }
+    }
+    tokens
+}
+
 fn cmd_show_config_yang(
     config: &DataTree<'static>,
     format: DataFormat,
@@ -623,13 +1104,14 @@ pub fn cmd_show_config(
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
     // Parse parameters.
-    let config_type = get_arg(&mut args, "configuration");
+    let config_type = get_arg(&mut args, "configuration")?;
     let config_type = match config_type.as_str() {
         "candidate" => ConfigurationType::Candidate,
         "running" => ConfigurationType::Running,
-        _ => panic!("unexpected argument"),
+        _ => return Err(format!("unknown configuration: {}", config_type)),
     };
     let with_defaults = get_opt_arg(&mut args, "with-defaults").is_some();
+    let annotate = get_opt_arg(&mut args, "annotate").is_some();
     let format = get_opt_arg(&mut args, "format");
 
     // Get configuration.
@@ -643,11 +1125,12 @@ pub fn cmd_show_config(
         Some("xml") => {
             cmd_show_config_yang(config, DataFormat::XML, with_defaults)?
         }
-        Some(_) => panic!("unknown format"),
-        None => cmd_show_config_cmds(config, with_defaults),
+        Some(format) => return Err(format!("unknown format: {}", format)),
+        None => cmd_show_config_cmds(config, with_defaults, annotate),
     };
     if let Err(error) = page_output(session, &data) {
-        println!("% failed to print configuration: {}", error)
+        session
+            .output_line(&format!("% failed to print configuration: {}", error))
     }
 
     Ok(false)
@@ -656,24 +1139,167 @@ pub fn cmd_show_config(
 pub fn cmd_show_config_changes(
     _commands: &Commands,
     session: &mut Session,
-    _args: ParsedArgs,
+    mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let running = session.get_configuration(ConfigurationType::Running);
-    let running = cmd_show_config_cmds(running, false);
-    let candidate = session.get_configuration(ConfigurationType::Candidate);
-    let candidate = cmd_show_config_cmds(candidate, false);
-
-    let diff = TextDiff::from_lines(&running, &candidate);
-    print!(
-        "{}",
-        diff.unified_diff()
-            .context_radius(9)
-            .header("running configuration", "candidate configuration")
-    );
+    let format = get_opt_arg(&mut args, "format");
+
+    let output = match format.as_deref() {
+        Some("yang-patch") => config_changes_yang_patch(session)?,
+        Some(format) => return Err(format!("unknown format: {}", format)),
+        None => config_changes(session)?,
+    };
+    if let Err(error) = page_output(session, &output) {
+        session.output_line(&format!(
+            "% failed to print configuration changes: {}",
+            error
+        ))
+    }
 
     Ok(false)
 }
 
+// Returns the changes made to the candidate configuration, one command per
+// line, prefixed by "+" (created), "-" (deleted) or "~" (replaced).
+//
+// Created and deleted subtrees are expanded into all of their commands.
+fn config_changes(session: &mut Session) -> Result<String, String> {
+    let diff = session
+        .candidate_diff()
+        .map_err(|error| error.to_string())?;
+
+    let mut output = String::new();
+    for (op, dnode) in diff.iter() {
+        match op {
+            DataDiffOp::Create | DataDiffOp::Delete => {
+                let sign = if op == DataDiffOp::Create { '+' } else { '-' };
+                for dnode in dnode
+                    .traverse()
+                    .filter(is_config_cmd)
+                    .filter(|dnode| !dnode.is_default())
+                {
+                    let tokens = config_cmd_tokens(&dnode);
+                    writeln!(output, "{} {}", sign, cli_cmd(&dnode, &tokens))
+                        .unwrap();
+                }
+            }
+            DataDiffOp::Replace => {
+                let tokens = config_cmd_tokens(&dnode);
+                write!(output, "~ {}", cli_cmd(&dnode, &tokens)).unwrap();
+                // Leafs have their previous value recorded, while
+                // user-ordered entries have their previous position.
+                match dnode.meta().find(|meta| meta.name() == "orig-value") {
+                    Some(meta)
+                        if dnode.schema().kind() == SchemaNodeKind::Leaf =>
+                    {
+                        write!(output, " (was {})", meta.value()).unwrap();
+                    }
+                    _ => write!(output, " (moved)").unwrap(),
+                }
+                writeln!(output).unwrap();
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+// Returns the changes made to the candidate configuration as a YANG patch
+// (RFC 8072), in JSON format.
+fn config_changes_yang_patch(session: &mut Session) -> Result<String, String> {
+    let diff = session
+        .candidate_diff()
+        .map_err(|error| error.to_string())?;
+    let candidate = session.get_configuration(ConfigurationType::Candidate);
+
+    let mut edits = vec![];
+    for (op, dnode) in diff.iter() {
+        let operation = match op {
+            DataDiffOp::Create => "create",
+            DataDiffOp::Delete => "delete",
+            DataDiffOp::Replace => "replace",
+        };
+        let mut edit = format!(
+            "{{\"edit-id\": \"{}\", \"operation\": \"{}\", \"target\": {}",
+            edits.len() + 1,
+            operation,
+            json_string(&restconf_path(&dnode)),
+        );
+
+        // The value of created and replaced nodes comes from the candidate
+        // configuration, as the diff lacks the unchanged descendants.
+        if op != DataDiffOp::Delete {
+            let value = candidate
+                .find_path(&dnode.path())
+                .and_then(|dnode| dnode.duplicate(false))
+                .and_then(|dtree| {
+                    dtree.print_string(
+                        DataFormat::JSON,
+                        DataPrinterFlags::SHRINK,
+                    )
+                })
+                .map_err(|error| error.to_string())?;
+            write!(edit, ", \"value\": {}", value.trim()).unwrap();
+        }
+        edit.push('}');
+        edits.push(edit);
+    }
+
+    Ok(format!(
+        "{{\"ietf-yang-patch:yang-patch\": {{\"patch-id\": \"candidate\", \
+         \"edit\": [{}]}}}}\n",
+        edits.join(", ")
+    ))
+}
+
+// Returns the command line of a data node, including the path of its closest
+// list ancestor.
+fn cli_cmd(dnode: &DataNodeRef<'_>, tokens: &[String]) -> String {
+    let mut cmd = dnode
+        .ancestors()
+        .filter(|dnode| dnode.schema().kind() == SchemaNodeKind::List)
+        .collect::<Vec<_>>()
+        .iter()
+        .rev()
+        .map(|dnode| config_cmd_tokens(dnode).join(" "))
+        .collect::<Vec<_>>();
+    cmd.push(tokens.join(" "));
+    cmd.join(" ")
+}
+
+// Builds the RESTCONF path of a data node (RFC 8040, section 3.5.3).
+fn restconf_path(dnode: &DataNodeRef<'_>) -> String {
+    let mut path = String::new();
+    let dnodes = dnode.inclusive_ancestors().collect::<Vec<_>>();
+    for (i, dnode) in dnodes.iter().enumerate().rev() {
+        let snode = dnode.schema();
+        // The module name is only needed when it differs from the parent's.
+        let module = snode.module();
+        match dnodes.get(i + 1) {
+            Some(parent) if parent.schema().module() == module => {
+                write!(path, "/{}", snode.name()).unwrap();
+            }
+            _ => write!(path, "/{}:{}", module.name(), snode.name()).unwrap(),
+        }
+        let keys = match snode.kind() {
+            SchemaNodeKind::List => dnode
+                .list_keys()
+                .map(|dnode| dnode.value_canonical().unwrap_or_default())
+                .collect::<Vec<_>>(),
+            SchemaNodeKind::LeafList => {
+                vec![dnode.value_canonical().unwrap_or_default()]
+            }
+            _ => continue,
+        };
+        let keys = keys
+            .iter()
+            .map(|key| percent_encode(key))
+            .collect::<Vec<_>>()
+            .join(",");
+        write!(path, "={}", keys).unwrap();
+    }
+    path
+}
+
 // ===== "show state" =====
 
 pub fn cmd_show_state(
@@ -686,7 +1312,7 @@ pub fn cmd_show_state(
     let format = match format.as_deref() {
         Some("json") => DataFormat::JSON,
         Some("xml") => DataFormat::XML,
-        Some(_) => panic!("unknown format"),
+        Some(format) => return Err(format!("unknown format: {}", format)),
         None => DataFormat::JSON,
     };
 
@@ -694,11 +1320,120 @@ pub fn cmd_show_state(
     {
         Ok(proto::data_tree::Data::DataString(data)) => {
             if let Err(error) = page_output(session, &data) {
-                println!("% failed to print state data: {}", error)
+                session.output_line(&format!(
+                    "% failed to print state data: {}",
+                    error
+                ))
             }
         }
-        Ok(proto::data_tree::Data::DataBytes(_)) => unreachable!(),
-        Err(error) => println!("% failed to fetch state data: {}", error),
+        Ok(proto::data_tree::Data::DataBytes(_)) => session.output_line(
+            "% failed to fetch state data: unexpected binary data",
+        ),
+        Err(error) => session
+            .output_line(&format!("% failed to fetch state data: {}", error)),
+    }
+
+    Ok(false)
+}
+
+// ===== "show connections" =====
+
+pub fn cmd_show_connections(
+    _commands: &Commands,
+    session: &mut Session,
+    _args: ParsedArgs,
+) -> Result<bool, String> {
+    // Create the table
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["", "Name", "Address", "Hostname", "Status"]);
+
+    // Add a row per connection
+    for connection in session.connections() {
+        let status = if connection.connected {
+            "connected"
+        } else {
+            "disconnected"
+        };
+        table.add_row(row![
+            if connection.active { "*" } else { "" },
+            connection.name,
+            connection.address,
+            connection.hostname,
+            status
+        ]);
+    }
+
+    // Print the table to stdout
+    if let Err(error) = page_table(session, &table) {
+        session.output_line(&format!("% failed to display data: {}", error));
+    }
+
+    Ok(false)
+}
+
+// ===== "show version" =====
+
+pub fn cmd_show_version(
+    _commands: &Commands,
+    session: &mut Session,
+    _args: ParsedArgs,
+) -> Result<bool, String> {
+    session.output_line(&format!(
+        "holo-cli version: {}",
+        env!("CARGO_PKG_VERSION")
+    ));
+    let Some(capabilities) = session.capabilities().cloned() else {
+        session.output_line("holod version: - (offline)");
+        session.output_line("");
+        return Ok(false);
+    };
+
+    let encodings = capabilities
+        .supported_encodings()
+        .map(|encoding| encoding.as_str_name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    session.output_line(&format!("holod version: {}", capabilities.version));
+    session.output_line(&format!(
+        "holod address: {}",
+        session.address().unwrap_or("-")
+    ));
+    session.output_line(&format!(
+        "YANG modules: {}",
+        capabilities.supported_modules.len()
+    ));
+    session.output_line(&format!(
+        "Supported encodings: {}",
+        if encodings.is_empty() {
+            "-"
+        } else {
+            &encodings
+        }
+    ));
+    session.output_line("");
+
+    // Create the table
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Module", "Revision", "Features"]);
+
+    // Add a row per module
+    for module in &capabilities.supported_modules {
+        table.add_row(row![
+            module.name,
+            if module.revision.is_empty() {
+                "-"
+            } else {
+                &module.revision
+            },
+            module.supported_features.join(", ")
+        ]);
+    }
+
+    // Print the table to stdout
+    if let Err(error) = page_table(session, &table) {
+        session.output_line(&format!("% failed to display data: {}", error));
     }
 
     Ok(false)
@@ -708,7 +1443,7 @@ pub fn cmd_show_state(
 
 pub fn cmd_show_yang_modules(
     _commands: &Commands,
-    _session: &mut Session,
+    session: &mut Session,
     _args: ParsedArgs,
 ) -> Result<bool, String> {
     // Create the table
@@ -734,14 +1469,141 @@ pub fn cmd_show_yang_modules(
     }
 
     // Print the table to stdout
-    println!(" Flags: I - Implemented");
-    println!();
-    table.printstd();
-    println!();
+    session.output_line(" Flags: I - Implemented");
+    session.output_line("");
+    if let Err(error) = page_table(session, &table) {
+        session.output_line(&format!("% failed to display data: {}", error));
+    }
 
     Ok(false)
 }
 
+// ===== "show yang cache" =====
+
+pub fn cmd_show_yang_cache(
+    _commands: &Commands,
+    session: &mut Session,
+    _args: ParsedArgs,
+) -> Result<bool, String> {
+    let cache_dir = YANG_CACHE_DIR.get().unwrap();
+
+    // Create the table
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Daemon Version", "Modules", "Size"]);
+
+    // Add a row per daemon version
+    let mut entries = std::fs::read_dir(cache_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let files = std::fs::read_dir(entry.path())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .collect::<Vec<_>>();
+        let modules = files
+            .iter()
+            .filter(|file| {
+                file.path().extension().is_some_and(|ext| ext == "yang")
+            })
+            .count();
+        let size = files
+            .iter()
+            .filter_map(|file| file.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum::<u64>();
+        table.add_row(row![
+            entry.file_name().to_string_lossy(),
+            modules,
+            format!("{} KiB", size.div_ceil(1024))
+        ]);
+    }
+
+    // Print the table to stdout
+    session.output_line(&format!(" Directory: {}", cache_dir));
+    session.output_line("");
+    if let Err(error) = page_table(session, &table) {
+        session.output_line(&format!("% failed to display data: {}", error));
+    }
+
+    Ok(false)
+}
+
+// ===== "yang cache clear" =====
+
+pub fn cmd_yang_cache_clear(
+    _commands: &Commands,
+    session: &mut Session,
+    _args: ParsedArgs,
+) -> Result<bool, String> {
+    let cache_dir = YANG_CACHE_DIR.get().unwrap();
+    let entries = match std::fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(false);
+        }
+        Err(error) => {
+            return Err(format!(
+                "failed to clear YANG modules cache: {}",
+                error
+            ));
+        }
+    };
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+
+        // Only touch the directories created by holo-cli.
+        if !path.join(CACHE_SENTINEL).is_file() {
+            session.output_line(&format!(
+                "% skipping {}: not a YANG modules cache directory",
+                path.display()
+            ));
+            continue;
+        }
+        if let Err(error) = yang_cache_clear_dir(&path) {
+            return Err(format!(
+                "failed to clear {}: {}",
+                path.display(),
+                error
+            ));
+        }
+    }
+
+    Ok(false)
+}
+
+// Removes the cached YANG modules and their digests from the given directory,
+// removing the directory itself if nothing else is left in it.
+fn yang_cache_clear_dir(path: &std::path::Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(path)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_file()
+            && (name.ends_with(".yang")
+                || name.ends_with(".yang.sha256")
+                || name == CACHE_MODULE_LIST
+                || name.starts_with(CACHE_COMMAND_INDEX_PREFIX))
+        {
+            std::fs::remove_file(&path)?;
+        }
+    }
+
+    if std::fs::read_dir(path)?.count() == 1 {
+        std::fs::remove_file(path.join(CACHE_SENTINEL))?;
+        std::fs::remove_dir(path)?;
+    }
+
+    Ok(())
+}
+
 // ===== IS-IS "show" commands =====
 
 const PROTOCOL_ISIS: &str = "ietf-isis:isis";
@@ -908,7 +1770,7 @@ pub fn cmd_show_ospf_interface(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -950,7 +1812,7 @@ pub fn cmd_show_ospf_interface_detail(
     let mut output = String::new();
 
     // Parse arguments.
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1010,7 +1872,7 @@ pub fn cmd_show_ospf_interface_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -1021,7 +1883,7 @@ pub fn cmd_show_ospf_vlink(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1062,7 +1924,7 @@ pub fn cmd_show_ospf_neighbor(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1111,7 +1973,7 @@ pub fn cmd_show_ospf_neighbor_detail(
     let mut output = String::new();
 
     // Parse arguments.
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1191,7 +2053,7 @@ pub fn cmd_show_ospf_neighbor_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -1202,7 +2064,7 @@ pub fn cmd_show_ospf_database_as(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1245,7 +2107,7 @@ pub fn cmd_show_ospf_database_area(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1290,7 +2152,7 @@ pub fn cmd_show_ospf_database_link(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1337,7 +2199,7 @@ pub fn cmd_show_ospf_route(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1365,7 +2227,7 @@ pub fn cmd_show_ospf_hostnames(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ospfv2" => PROTOCOL_OSPFV2,
         "ospfv3" => PROTOCOL_OSPFV3,
         _ => unreachable!(),
@@ -1427,7 +2289,7 @@ pub fn cmd_show_rip_interface(
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
     // Parse arguments.
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ripv2" => PROTOCOL_RIPV2,
         "ripng" => PROTOCOL_RIPNG,
         _ => unreachable!(),
@@ -1453,7 +2315,7 @@ pub fn cmd_show_rip_interface_detail(
     let mut output = String::new();
 
     // Parse arguments.
-    let protocol = match get_arg(&mut args, "protocol").as_str() {
+    let protocol = match get_arg(&mut args, "protocol")?.as_str() {
         "ripv2" => PROTOCOL_RIPV2,
         "ripng" => PROTOCOL_RIPNG,
         _ => unreachable!(),
@@ -1512,7 +2374,7 @@ pub fn cmd_show_rip_interface_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -1524,12 +2386,12 @@ pub fn cmd_show_rip_neighbor(
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
     // Parse arguments.
-    let (protocol, afi, address) = match get_arg(&mut args, "protocol").as_str()
-    {
-        "ripv2" => (PROTOCOL_RIPV2, AFI4, "ipv4-address"),
-        "ripng" => (PROTOCOL_RIPNG, AFI6, "ipv6-address"),
-        _ => unreachable!(),
-    };
+    let (protocol, afi, address) =
+        match get_arg(&mut args, "protocol")?.as_str() {
+            "ripv2" => (PROTOCOL_RIPV2, AFI4, "ipv4-address"),
+            "ripng" => (PROTOCOL_RIPNG, AFI6, "ipv6-address"),
+            _ => unreachable!(),
+        };
 
     let xpath_rip_neighbor = format!("ietf-rip:rip/{}/neighbors/neighbor", afi);
 
@@ -1554,12 +2416,12 @@ pub fn cmd_show_rip_neighbor_detail(
     let mut output = String::new();
 
     // Parse arguments.
-    let (protocol, afi, address) = match get_arg(&mut args, "protocol").as_str()
-    {
-        "ripv2" => (PROTOCOL_RIPV2, "ipv4", "ipv4-address"),
-        "ripng" => (PROTOCOL_RIPNG, "ipv6", "ipv6-address"),
-        _ => unreachable!(),
-    };
+    let (protocol, afi, address) =
+        match get_arg(&mut args, "protocol")?.as_str() {
+            "ripv2" => (PROTOCOL_RIPV2, "ipv4", "ipv4-address"),
+            "ripng" => (PROTOCOL_RIPNG, "ipv6", "ipv6-address"),
+            _ => unreachable!(),
+        };
 
     let nb_address = get_opt_arg(&mut args, "address");
 
@@ -1585,7 +2447,10 @@ pub fn cmd_show_rip_neighbor_detail(
         let instance = dnode.child_value("name");
 
         // Iterate over RIP neighbors.
-        for dnode in dnode.find_xpath(&xpath_neighbor).unwrap() {
+        let neighbors = dnode.find_xpath(&xpath_neighbor).map_err(|error| {
+            format!("failed to find RIP neighbors: {}", error)
+        })?;
+        for dnode in neighbors {
             // "address" keyword is used to identify the afi address type
             writeln!(output, "{}", dnode.child_value(address)).unwrap();
             writeln!(output, " instance: {}", instance).unwrap();
@@ -1604,7 +2469,7 @@ pub fn cmd_show_rip_neighbor_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -1616,7 +2481,7 @@ pub fn cmd_show_rip_route(
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
     // Parse arguments.
-    let (protocol, afi, prefix) = match get_arg(&mut args, "protocol").as_str()
+    let (protocol, afi, prefix) = match get_arg(&mut args, "protocol")?.as_str()
     {
         "ripv2" => (PROTOCOL_RIPV2, AFI4, "ipv4-prefix"),
         "ripng" => (PROTOCOL_RIPNG, AFI6, "ipv6-prefix"),
@@ -1767,7 +2632,7 @@ pub fn cmd_show_mpls_ldp_discovery_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -1944,7 +2809,7 @@ pub fn cmd_show_mpls_ldp_peer_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -2129,14 +2994,15 @@ fn bgp_get_attrs(
     let data =
         fetch_data(session, proto::get_request::DataType::State, &xpath)?;
 
-    let attributes = data
-        .find_path(&xpath)
-        .unwrap()
+    let Ok(dnode) = data.find_path(&xpath) else {
+        return Ok(BTreeMap::new());
+    };
+    let attributes = dnode
         .find_xpath("attr-set")
-        .unwrap()
-        .map(|dnode| {
+        .map_err(|error| format!("failed to find BGP attributes: {}", error))?
+        .filter_map(|dnode| {
             let index = dnode.child_value("index");
-            let attrs = dnode.find_path("attributes").unwrap();
+            let attrs = dnode.find_path("attributes").ok()?;
 
             let nexthop =
                 attrs.child_opt_value("next-hop").unwrap_or("-".to_owned());
@@ -2156,18 +3022,19 @@ fn bgp_get_attrs(
 
             let as_path = attrs
                 .find_xpath("as-path/segment/member")
-                .unwrap()
+                .into_iter()
+                .flatten()
                 .filter_map(|member| member.value_canonical())
                 .collect::<Vec<String>>()
                 .join(" ");
 
-            (
+            Some((
                 index,
                 format!(
                     "{:>20} {:>5} {:>9} {} {}",
                     nexthop, med, lclpref, as_path, origin
                 ),
-            )
+            ))
         })
         .collect();
 
@@ -2179,12 +3046,12 @@ pub fn cmd_show_bgp_neighbor(
     session: &mut Session,
     mut args: ParsedArgs,
 ) -> Result<bool, String> {
-    let attrs = bgp_get_attrs(session).unwrap();
+    let attrs = bgp_get_attrs(session)?;
 
     let mut output = String::new();
 
-    let neighbor = get_arg(&mut args, "neighbor");
-    let rt_type = get_arg(&mut args, "type");
+    let neighbor = get_arg(&mut args, "neighbor")?;
+    let rt_type = get_arg(&mut args, "type")?;
     let afi = get_opt_arg(&mut args, "afi").unwrap_or("ipv4".to_owned());
 
     let afi = match afi.as_str() {
@@ -2223,15 +3090,21 @@ pub fn cmd_show_bgp_neighbor(
         "Prefix", "NextHop", "MED", "LocalPref"
     )
     .unwrap();
-    for route in data.find_xpath(&xpath_routes).unwrap() {
-        let prefix = route.child_opt_value("prefix").unwrap();
-        let index = route.child_opt_value("attr-index").unwrap();
-        let route_attrs = attrs.get(&index).unwrap();
+    let routes = data.find_xpath(&xpath_routes).map_err(|error| {
+        format!("failed to find BGP neighbor routes: {}", error)
+    })?;
+    for route in routes {
+        let prefix = route.child_value("prefix");
+        let route_attrs = route
+            .child_opt_value("attr-index")
+            .and_then(|index| attrs.get(&index))
+            .map(String::as_str)
+            .unwrap_or("-");
         writeln!(output, "{:>20} {}", prefix, route_attrs).unwrap();
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -2274,7 +3147,11 @@ pub fn cmd_show_bgp_neighbor_detail(
         let local_rid =
             dnode_inst.relative_value("ietf-bgp:bgp/global/identifier");
 
-        for dnode_nbr in dnode_inst.find_xpath(&xpath_neighbor).unwrap() {
+        let neighbors =
+            dnode_inst.find_xpath(&xpath_neighbor).map_err(|error| {
+                format!("failed to find BGP neighbors: {}", error)
+            })?;
+        for dnode_nbr in neighbors {
             let remote_addr = dnode_nbr.child_value("remote-address");
             let remote_as = dnode_nbr.child_value("peer-as");
             let peer_type = dnode_nbr.child_value("peer-type");
@@ -2470,7 +3347,7 @@ pub fn cmd_show_bgp_neighbor_detail(
     }
 
     if let Err(error) = page_output(session, &output) {
-        println!("% failed to print data: {}", error)
+        session.output_line(&format!("% failed to print data: {}", error))
     }
 
     Ok(false)
@@ -2547,21 +3424,23 @@ pub fn cmd_clear_bgp_neighbor(
             op => op,
         };
         let xpath = format!("{}/{}", &xpath, operation);
-        clear_req.new_path(&xpath, None, false).unwrap();
+        clear_req
+            .new_path(&xpath, None, false)
+            .map_err(|error| format!("invalid clear type: {}", error))?;
     }
 
     if neighbor.is_some() {
         let xpath = format!("{}/holo-bgp:remote-addr", &xpath);
         clear_req
             .new_path(&xpath, neighbor.as_deref(), false)
-            .unwrap();
+            .map_err(|error| format!("invalid neighbor address: {}", error))?;
     }
 
     let data = clear_req
         .print_string(DataFormat::JSON, DataPrinterFlags::WD_ALL)
         .unwrap();
 
-    println!("{}", data);
+    session.output_line(&data);
 
     let data = DataTree::parse_op_string(
         yang_ctx,
@@ -2663,3 +3542,29 @@ pub fn cmd_show_route(
 
     Ok(false)
 }
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_encode_reserved() {
+        assert_eq!(percent_encode("abc-XYZ_0.9~"), "abc-XYZ_0.9~");
+        assert_eq!(percent_encode("a b/c:d"), "a%20b%2Fc%3Ad");
+        assert_eq!(percent_encode("[name='eth0']"), "%5Bname%3D%27eth0%27%5D");
+        assert_eq!(percent_encode("é"), "%C3%A9");
+        assert_eq!(percent_encode(""), "");
+    }
+
+    #[test]
+    fn json_string_escapes() {
+        assert_eq!(json_string("foo"), "\"foo\"");
+        assert_eq!(json_string(""), "\"\"");
+        assert_eq!(json_string("say \"hi\""), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"a\b"), r#""a\\b""#);
+        assert_eq!(json_string("a\nb\t"), r#""a\u000ab\u0009""#);
+        assert_eq!(json_string("é"), "\"é\"");
+    }
+}
@@ -0,0 +1,201 @@
+//
+// Copyright (c) The Holo Core Contributors
+//
+// SPDX-License-Identifier: MIT
+//
+
+use prettytable::{Table, format};
+use serde::Deserialize;
+
+use crate::grpc::ConnectOptions;
+use crate::session::{CapturedOutput, Session};
+use crate::{Cli, YANG_CTX};
+
+// Inventory of Holo daemons.
+//
+// Example:
+//
+//   [[device]]
+//   name = "rt1"
+//   address = "10.0.0.1:50051"
+#[derive(Debug, Deserialize)]
+struct Inventory {
+    #[serde(rename = "device", default)]
+    devices: Vec<Device>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Device {
+    name: String,
+    address: String,
+}
+
+// ===== impl Inventory =====
+
+impl Inventory {
+    fn load(path: &str) -> Result<Inventory, String> {
+        let data = std::fs::read_to_string(path).map_err(|error| {
+            format!("failed to read inventory ({}): {}", path, error)
+        })?;
+        toml::from_str(&data).map_err(|error| {
+            format!("failed to parse inventory ({}): {}", path, error)
+        })
+    }
+}
+
+// ===== global functions =====
+
+// Runs the given commands on all devices of the inventory concurrently.
+//
+// When all devices output a single table with the same columns, the tables are
+// merged into one with a leading "Device" column. Otherwise, the output of each
+// device is displayed in a separate section.
+pub fn run(
+    path: &str,
+    options: &ConnectOptions,
+    commands: &[&str],
+) -> Result<(), String> {
+    let inventory = Inventory::load(path)?;
+
+    // Run the commands on all devices, each one in its own thread.
+    let outputs = std::thread::scope(|scope| {
+        let handles = inventory
+            .devices
+            .iter()
+            .map(|device| {
+                scope.spawn(move || run_device(device, options, commands))
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err("internal error".to_owned()))
+            })
+            .collect::<Vec<_>>()
+    });
+    let outputs = inventory.devices.iter().zip(outputs).collect::<Vec<_>>();
+
+    // Display the results.
+    if !print_merged(&outputs) {
+        print_sections(&outputs);
+    }
+
+    Ok(())
+}
+
+// Connects to the device and runs the given commands, capturing their output.
+fn run_device(
+    device: &Device,
+    options: &ConnectOptions,
+    commands: &[&str],
+) -> Result<Vec<CapturedOutput>, String> {
+    let (grpc_client, yang_ctx) = crate::connect(&device.address, options)?;
+    YANG_CTX.set(yang_ctx);
+
+    let session =
+        Session::new(false, &device.address, grpc_client, options.clone())
+            .map_err(|error| {
+                format!("failed to fetch running configuration: {}", error)
+            })?;
+    let mut cli = Cli::new(session);
+    cli.session.update_hostname();
+    cli.session.capture_start();
+    for command in commands {
+        if let Err(error) = cli.enter_command(command) {
+            let error = format!("% {}\n", error);
+            cli.session
+                .capture_mut()
+                .unwrap()
+                .push(CapturedOutput::Text(error));
+        }
+    }
+
+    Ok(cli.session.capture_stop())
+}
+
+// Prints the tables output by all devices as a single table, returning false if
+// the output can't be merged.
+fn print_merged(
+    outputs: &[(&Device, Result<Vec<CapturedOutput>, String>)],
+) -> bool {
+    // Check that all devices output a single table with the same columns.
+    let mut titles = None;
+    for (_, output) in outputs {
+        match output.as_deref() {
+            Ok([CapturedOutput::Table { titles: t, .. }]) => {
+                if titles.is_some_and(|titles| titles != t) {
+                    return false;
+                }
+                titles = Some(t);
+            }
+            Ok([]) | Err(_) => (),
+            Ok(_) => return false,
+        }
+    }
+    let Some(titles) = titles else {
+        return false;
+    };
+
+    // Create the table.
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(
+        std::iter::once("Device")
+            .chain(titles.iter().map(String::as_str))
+            .collect(),
+    );
+    for (device, output) in outputs {
+        if let Ok([CapturedOutput::Table { rows, .. }]) = output.as_deref() {
+            for row in rows {
+                table.add_row(
+                    std::iter::once(device.name.as_str())
+                        .chain(row.iter().map(String::as_str))
+                        .collect(),
+                );
+            }
+        }
+    }
+
+    // Print the table, followed by the devices that couldn't be reached.
+    table.printstd();
+    println!();
+    for (device, output) in outputs {
+        if let Err(error) = output {
+            println!("% {}: {}", device.name, error);
+        }
+    }
+
+    true
+}
+
+// Prints the output of each device in a separate section.
+fn print_sections(outputs: &[(&Device, Result<Vec<CapturedOutput>, String>)]) {
+    for (device, output) in outputs {
+        println!("===== {} ({}) =====", device.name, device.address);
+        println!();
+        match output {
+            Ok(output) => {
+                for output in output {
+                    match output {
+                        CapturedOutput::Text(text) => print!("{}", text),
+                        CapturedOutput::Table { titles, rows } => {
+                            let mut table = Table::new();
+                            table.set_format(
+                                *format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR,
+                            );
+                            table.set_titles(titles.iter().collect());
+                            for row in rows {
+                                table.add_row(row.iter().collect());
+                            }
+                            table.printstd();
+                            println!();
+                        }
+                    }
+                }
+            }
+            Err(error) => println!("% {}", error),
+        }
+    }
+}
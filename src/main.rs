@@ -4,9 +4,11 @@
 // SPDX-License-Identifier: MIT
 //
 
+mod cli_path;
 mod error;
 mod grpc;
 mod internal_commands;
+mod inventory;
 mod parser;
 mod session;
 mod terminal;
@@ -14,22 +16,41 @@ mod token;
 mod token_xml;
 mod token_yang;
 
+use std::cell::Cell;
+use std::io::IsTerminal;
 use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use reedline::Signal;
 use yang4::context::{Context, ContextFlags};
 
 use crate::error::Error;
-use crate::grpc::GrpcClient;
+use crate::grpc::{
+    CACHE_MODULE_LIST, ConnectOptions, Credentials, GrpcClient, ModuleList,
+    TlsOptions,
+};
 use crate::session::{CommandMode, Session};
 use crate::terminal::CliPrompt;
 use crate::token::{Action, Commands};
 
 // Global YANG context.
-pub static YANG_CTX: OnceLock<Arc<Context>> = OnceLock::new();
+//
+// Each connection has its own YANG context, so this refers to the YANG context
+// of the active connection. It's tracked per thread so that commands can run
+// against several devices concurrently.
+pub static YANG_CTX: YangContext = YangContext;
+
+thread_local! {
+    static ACTIVE_YANG_CTX: Cell<Option<&'static Context>> =
+        const { Cell::new(None) };
+}
 
-// Default YANG modules cache directory.
+// YANG modules cache directory.
+pub static YANG_CACHE_DIR: OnceLock<String> = OnceLock::new();
+
+// Default YANG modules cache directory, used when the user's cache directory
+// can't be determined.
 pub const YANG_MODULES_DIR: &str = "/usr/local/share/holo-cli/modules";
 
 pub struct Cli {
@@ -37,21 +58,24 @@ pub struct Cli {
     session: Session,
 }
 
+pub struct YangContext;
+
 // ===== impl Cli =====
 
 impl Cli {
-    fn new(use_pager: bool, grpc_client: GrpcClient) -> Cli {
+    fn new(session: Session) -> Cli {
         // Generate commands.
         let mut commands = Commands::new();
         commands.gen_cmds();
 
-        // Create CLI session.
-        let session = Session::new(use_pager, grpc_client);
-
-        Cli { commands, session }
+        let mut cli = Cli { commands, session };
+        cli.update_commands();
+        cli
     }
 
     fn enter_command(&mut self, line: &str) -> Result<bool, Error> {
+        self.update_commands();
+
         // Normalize input line.
         let line = match parser::normalize_input_line(line) {
             Some(line) => line,
@@ -83,9 +107,42 @@ impl Cli {
                 }
             }
         }
+        self.update_commands();
 
         Ok(exit)
     }
+
+    // Updates the command tree according to the session state.
+    fn update_commands(&mut self) {
+        // Regenerate commands if the active connection changed.
+        if self.session.take_yang_ctx_changed() {
+            self.commands = Commands::new();
+            self.commands.gen_cmds();
+        }
+
+        // Commands generated from the YANG modules are loaded from the index
+        // saved for the active module set, if any. Otherwise, they're only
+        // needed in configuration mode, so defer generating them until then.
+        if !self.commands.has_yang_cmds() {
+            let index_path = self.session.command_index_path();
+            self.commands.gen_yang_cmds(
+                index_path.as_deref(),
+                self.session.mode().is_configure(),
+            );
+        }
+    }
+}
+
+// ===== impl YangContext =====
+
+impl YangContext {
+    pub fn get(&self) -> Option<&'static Context> {
+        ACTIVE_YANG_CTX.get()
+    }
+
+    pub fn set(&self, yang_ctx: &'static Context) {
+        ACTIVE_YANG_CTX.set(Some(yang_ctx));
+    }
 }
 
 // ===== global functions =====
@@ -118,6 +175,167 @@ fn read_config_file(mut cli: Cli, path: &str) {
     }
 }
 
+// Loads the YANG modules cached in the given directory.
+//
+// The same modules and features advertised by holod when the cache was
+// populated are loaded, so that offline mode accepts the same configurations.
+fn load_modules_offline(
+    yang_ctx: &mut Context,
+    dir: &str,
+) -> Result<(), String> {
+    let module_list = ModuleList::load(dir)?;
+    yang_ctx
+        .set_searchdir(dir)
+        .map_err(|error| error.to_string())?;
+
+    // Load YANG modules.
+    for module in &module_list.modules {
+        let features = module
+            .features
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        yang_ctx
+            .load_module(&module.name, module.revision.as_deref(), &features)
+            .map_err(|error| {
+                format!(
+                    "failed to load YANG module ({}): {}",
+                    module.name, error
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
+// Builds the options used to connect to the Holo daemon from the
+// command-line parameters.
+fn connect_options(matches: &ArgMatches<'_>) -> ConnectOptions {
+    // TLS options.
+    let tls = ["ca-cert", "client-cert", "tls-domain"]
+        .iter()
+        .any(|name| matches.is_present(name))
+        .then(|| TlsOptions {
+            ca_cert: matches.value_of("ca-cert").map(str::to_owned),
+            client_cert: matches.value_of("client-cert").map(str::to_owned),
+            client_key: matches.value_of("client-key").map(str::to_owned),
+            domain: matches.value_of("tls-domain").map(str::to_owned),
+        });
+
+    // Operator credentials.
+    let token =
+        match (matches.value_of("token"), matches.value_of("token-file")) {
+            (Some(token), _) => Some(token.to_owned()),
+            (None, Some(path)) => match std::fs::read_to_string(path) {
+                Ok(token) => Some(token.trim().to_owned()),
+                Err(error) => {
+                    eprintln!(
+                        "Failed to read token file ({}): {}",
+                        path, error
+                    );
+                    std::process::exit(1);
+                }
+            },
+            (None, None) => std::env::var("HOLO_TOKEN").ok(),
+        };
+    let username = std::env::var("USER")
+        .or_else(|_| std::env::var("LOGNAME"))
+        .ok();
+    let credentials = Credentials { token, username };
+
+    // Request timeout.
+    let timeout = match matches.value_of("timeout").map(str::parse::<u64>) {
+        Some(Ok(0)) | None => None,
+        Some(Ok(seconds)) => Some(Duration::from_secs(seconds)),
+        Some(Err(error)) => {
+            eprintln!("Invalid timeout: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    ConnectOptions {
+        tls,
+        credentials,
+        timeout,
+    }
+}
+
+// Connects to the Holo daemon and loads the YANG modules it supports into a
+// new YANG context.
+//
+// The YANG context is leaked since the data trees of all connections borrow
+// it for the lifetime of the program.
+pub fn connect(
+    address: &str,
+    options: &ConnectOptions,
+) -> Result<(GrpcClient, &'static Context), String> {
+    // Prepend http:// (or https:// when TLS is enabled) if not already present
+    let addr = if address.starts_with("http://")
+        || address.starts_with("https://")
+        || address.starts_with("unix://")
+    {
+        address.to_owned()
+    } else if options.tls.is_some() {
+        format!("https://{}", address)
+    } else {
+        format!("http://{}", address)
+    };
+    if options.tls.is_some() && !addr.starts_with("https://") {
+        return Err("TLS options require an https:// address".to_owned());
+    }
+
+    // Connect to the daemon.
+    let mut grpc_client = GrpcClient::connect(&addr, options)
+        .map_err(|error| format!("connection to holod failed: {}", error))?;
+
+    // Load YANG modules.
+    let mut yang_ctx = new_yang_context();
+    grpc_client.load_modules(&mut yang_ctx)?;
+
+    Ok((grpc_client, Box::leak(Box::new(yang_ctx))))
+}
+
+// Returns the YANG modules cache directory of the most recently used daemon
+// version.
+fn latest_modules_dir() -> String {
+    let cache_dir = YANG_CACHE_DIR.get().unwrap();
+    std::fs::read_dir(cache_dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join(CACHE_MODULE_LIST).is_file())
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .unwrap_or_else(|| cache_dir.clone())
+}
+
+// Determines the YANG modules cache directory.
+//
+// In order of preference: the --yang-cache option, the $HOLO_CLI_CACHE
+// environment variable, or the XDG cache directory of the user.
+fn yang_cache_dir(matches: &ArgMatches<'_>) -> String {
+    if let Some(dir) = matches.value_of("yang-cache") {
+        return dir.to_owned();
+    }
+    let env = |name| std::env::var(name).ok().filter(|dir| !dir.is_empty());
+    if let Some(dir) = env("HOLO_CLI_CACHE") {
+        return dir;
+    }
+    if let Some(dir) = env("XDG_CACHE_HOME") {
+        return format!("{}/holo-cli/modules", dir);
+    }
+    if let Some(dir) = env("HOME") {
+        return format!("{}/.cache/holo-cli/modules", dir);
+    }
+    YANG_MODULES_DIR.to_owned()
+}
+
+// Creates a new, empty YANG context.
+fn new_yang_context() -> Context {
+    Context::new(ContextFlags::NO_YANGLIBRARY | ContextFlags::PREFER_SEARCHDIRS)
+        .unwrap()
+}
+
 fn main() {
     // Parse command-line parameters.
     let matches = App::new("Holo command-line interface")
@@ -151,62 +369,176 @@ fn main() {
                 .short("a")
                 .long("address")
                 .value_name("ADDRESS")
-                .help("Holo daemon IPv4/6 address: http://IP:Port")
+                .help(
+                    "Holo daemon address: http(s)://IP:Port or \
+                     unix:///path/to/socket",
+                )
                 .multiple(false),
         )
+        .arg(
+            Arg::with_name("ca-cert")
+                .long("ca-cert")
+                .value_name("FILE")
+                .help("CA certificate bundle used to verify holod (PEM)"),
+        )
+        .arg(
+            Arg::with_name("client-cert")
+                .long("client-cert")
+                .value_name("FILE")
+                .help("Client certificate for mutual TLS (PEM)")
+                .requires("client-key"),
+        )
+        .arg(
+            Arg::with_name("client-key")
+                .long("client-key")
+                .value_name("FILE")
+                .help("Client private key for mutual TLS (PEM)")
+                .requires("client-cert"),
+        )
+        .arg(
+            Arg::with_name("tls-domain")
+                .long("tls-domain")
+                .value_name("NAME")
+                .help("Domain name used to verify the holod certificate"),
+        )
+        .arg(
+            Arg::with_name("token")
+                .long("token")
+                .value_name("TOKEN")
+                .help(
+                    "Bearer token sent to holod with every request \
+                     (default: $HOLO_TOKEN)",
+                ),
+        )
+        .arg(
+            Arg::with_name("token-file")
+                .long("token-file")
+                .value_name("FILE")
+                .help("Read the bearer token from a file")
+                .conflicts_with("token"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Timeout for requests to holod (0 to disable)"),
+        )
+        .arg(
+            Arg::with_name("inventory")
+                .long("inventory")
+                .value_name("FILE")
+                .help("Run the given commands on all devices of an inventory")
+                .requires("command")
+                .conflicts_with_all(&["address", "file"]),
+        )
+        .arg(
+            Arg::with_name("yang-cache")
+                .long("yang-cache")
+                .value_name("DIR")
+                .help(
+                    "YANG modules cache directory (default: $HOLO_CLI_CACHE \
+                     or $XDG_CACHE_HOME/holo-cli/modules)",
+                ),
+        )
+        .arg(
+            Arg::with_name("offline")
+                .long("offline")
+                .help("Work offline, without connecting to the Holo daemon")
+                .conflicts_with_all(&[
+                    "address",
+                    "ca-cert",
+                    "client-cert",
+                    "client-key",
+                    "tls-domain",
+                    "token",
+                    "token-file",
+                    "timeout",
+                    "inventory",
+                ]),
+        )
+        .arg(
+            Arg::with_name("modules")
+                .long("modules")
+                .value_name("DIR")
+                .help("YANG modules cache directory of a holod version (offline mode)")
+                .requires("offline"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .value_name("FILE")
+                .help("Local JSON/XML configuration datastore (offline mode)")
+                .requires("offline"),
+        )
         .get_matches();
 
-    // Connect to the daemon.
-    let raw_addr = matches
-        .value_of("address")
-        .unwrap_or("127.0.0.1:50051") // no http:// in default
-        .to_string();
-
-    // Prepend http:// if not already present
-    let addr = if raw_addr.starts_with("http://")
-        || raw_addr.starts_with("https://")
-    {
-        raw_addr
-    } else {
-        format!("http://{}", raw_addr)
-    };
-
-    let grpc_addr: &'static str = Box::leak(addr.into_boxed_str());
-    let mut grpc_client = match GrpcClient::connect(grpc_addr) {
-        Ok(grpc_client) => grpc_client,
-        Err(error) => {
-            eprintln!("Connection to holod failed: {}\n", error);
-            eprintln!("Please ensure that holod is currently running.");
+    let use_pager = matches.values_of("command").is_none()
+        && !matches.is_present("no-pager");
+    YANG_CACHE_DIR.set(yang_cache_dir(&matches)).unwrap();
+
+    // Run the given commands on all devices of the inventory.
+    if let Some(path) = matches.value_of("inventory") {
+        let commands =
+            matches.values_of("command").unwrap().collect::<Vec<_>>();
+        let options = connect_options(&matches);
+        if let Err(error) = inventory::run(path, &options, &commands) {
+            eprintln!("{}", error);
             std::process::exit(1);
         }
-    };
-
-    // Initialize YANG context.
-    let mut yang_ctx = Context::new(
-        ContextFlags::NO_YANGLIBRARY | ContextFlags::PREFER_SEARCHDIRS,
-    )
-    .unwrap();
-
-    // Ensure the YANG modules cache directory exists, creating it if necessary.
-    if let Err(error) = std::fs::create_dir_all(YANG_MODULES_DIR) {
-        eprintln!(
-            "Failed to create YANG modules directory ({}): {}",
-            YANG_MODULES_DIR, error
-        );
-        std::process::exit(1);
+        return;
     }
 
-    // Set YANG search directory.
-    yang_ctx.set_searchdir(YANG_MODULES_DIR).unwrap();
+    // In offline mode, load the YANG modules from the local filesystem and use
+    // a local configuration datastore.
+    let session = if matches.is_present("offline") {
+        let mut yang_ctx = new_yang_context();
+        let modules_dir = matches
+            .value_of("modules")
+            .map(str::to_owned)
+            .unwrap_or_else(latest_modules_dir);
+        let modules_dir = modules_dir.as_str();
+        if let Err(error) = load_modules_offline(&mut yang_ctx, modules_dir) {
+            eprintln!(
+                "Failed to load YANG modules from {}: {}",
+                modules_dir, error
+            );
+            std::process::exit(1);
+        }
+        YANG_CTX.set(Box::leak(Box::new(yang_ctx)));
 
-    // Load YANG modules.
-    grpc_client.load_modules(grpc_addr, &mut yang_ctx);
-    YANG_CTX.set(Arc::new(yang_ctx)).unwrap();
+        let datastore = matches.value_of("config").map(str::to_owned);
+        match Session::new_offline(use_pager, datastore, modules_dir) {
+            Ok(session) => session,
+            Err(error) => {
+                eprintln!("Failed to load configuration: {}", error);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let options = connect_options(&matches);
+        let address = matches.value_of("address").unwrap_or("127.0.0.1:50051");
+        let (grpc_client, yang_ctx) = match connect(address, &options) {
+            Ok(connection) => connection,
+            Err(error) => {
+                eprintln!("{}\n", error);
+                eprintln!("Please ensure that holod is currently running.");
+                std::process::exit(1);
+            }
+        };
+        YANG_CTX.set(yang_ctx);
+
+        // Create CLI session.
+        match Session::new(use_pager, address, grpc_client, options) {
+            Ok(session) => session,
+            Err(error) => {
+                eprintln!("Failed to fetch running configuration: {}", error);
+                std::process::exit(1);
+            }
+        }
+    };
 
     // Initialize CLI master structure.
-    let use_pager = matches.values_of("command").is_none()
-        && !matches.is_present("no-pager");
-    let mut cli = Cli::new(use_pager, grpc_client);
+    let mut cli = Cli::new(session);
 
     // Read configuration file.
     if let Some(path) = matches.value_of("file") {
@@ -227,6 +559,9 @@ fn main() {
         return;
     }
 
+    // Questions can only be answered when stdin is a terminal.
+    cli.session.interactive_set(std::io::stdin().is_terminal());
+
     // Initialize reedline.
     let mut prompt = CliPrompt::new(cli.session.prompt());
     let cli = Arc::new(Mutex::new(cli));
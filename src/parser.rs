@@ -22,22 +22,121 @@ pub struct ParsedCommand {
 
 pub type ParsedArgs = VecDeque<(String, String)>;
 
+// Word of a command line, along with its position in the line.
+#[derive(Debug)]
+pub struct Word {
+    pub value: String,
+    pub start: usize,
+    pub end: usize,
+}
+
 // ===== global functions =====
 
 pub fn normalize_input_line(line: &str) -> Option<String> {
-    // Ignore "!" comments.
-    // TODO: allow "!" within user input like interface descriptions
-    let line = line.split('!').next()?;
+    // Ignore empty input and "!" comments. Unterminated quotes are reported
+    // later by the parser.
+    if lex(line).is_ok_and(|words| words.is_empty()) {
+        return None;
+    }
 
-    // Remove redundant whitespaces.
-    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(line.trim().to_owned())
+}
 
-    // Handle empty input.
-    if line.is_empty() {
-        return None;
+// Splits a command line into words.
+//
+// Words are separated by whitespaces, unless quoted with single or double
+// quotes. A backslash escapes the following character, except within single
+// quotes. A "!" at the start of a word starts a comment that extends until the
+// end of the line.
+pub fn split_words(line: &str) -> Result<Vec<String>, ParserError> {
+    let words = lex(line)?;
+    Ok(words.into_iter().map(|word| word.value).collect())
+}
+
+// Quotes the given word if necessary, so that it's parsed back as a single
+// word with the same value.
+pub fn quote_word(word: &str) -> String {
+    let needs_quotes = word.is_empty()
+        || word.starts_with('!')
+        || word
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '\\'));
+    if !needs_quotes {
+        return word.to_owned();
     }
 
-    Some(line)
+    let mut quoted = String::from('"');
+    for c in word.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Splits a command line into words, keeping track of their positions.
+pub fn lex(line: &str) -> Result<Vec<Word>, ParserError> {
+    let mut words = vec![];
+    let mut word: Option<Word> = None;
+    let mut quote = None;
+    let mut chars = line.char_indices();
+
+    while let Some((pos, c)) = chars.next() {
+        let end = pos + c.len_utf8();
+        match (quote, c) {
+            // End of quoted string.
+            (Some(q), c) if c == q => quote = None,
+            // Escaped character within double quotes.
+            (Some('"'), '\\') => {
+                if let Some((pos, c)) = chars.next() {
+                    let word = word.as_mut().unwrap();
+                    word.value.push(c);
+                    word.end = pos + c.len_utf8();
+                    continue;
+                }
+                return Err(ParserError::UnterminatedQuote);
+            }
+            // Quoted character.
+            (Some(_), c) => word.as_mut().unwrap().value.push(c),
+            // End of word.
+            (None, c) if c.is_whitespace() => {
+                words.extend(word.take());
+                continue;
+            }
+            // Comment.
+            (None, '!') if word.is_none() => break,
+            (None, c) => {
+                let word = word.get_or_insert_with(|| Word {
+                    value: String::new(),
+                    start: pos,
+                    end,
+                });
+                match c {
+                    '\'' | '"' => quote = Some(c),
+                    '\\' => match chars.next() {
+                        Some((pos, c)) => {
+                            word.value.push(c);
+                            word.end = pos + c.len_utf8();
+                            continue;
+                        }
+                        None => word.value.push(c),
+                    },
+                    c => word.value.push(c),
+                }
+            }
+        }
+        if let Some(word) = &mut word {
+            word.end = end;
+        }
+    }
+    if quote.is_some() {
+        return Err(ParserError::UnterminatedQuote);
+    }
+    words.extend(word);
+
+    Ok(words)
 }
 
 fn get_tokens(
@@ -100,7 +199,9 @@ pub fn parse_command_try(
     let mut args = ParsedArgs::new();
     let mut negate = false;
 
-    for (index, word) in line.split_whitespace().enumerate() {
+    let words = lex(line)?;
+    for (index, word) in words.iter().enumerate() {
+        let word = word.value.as_str();
         let first_word = index == 0;
         let tokens = get_tokens(
             commands,
@@ -139,12 +240,24 @@ pub fn parse_command_try(
                 let value = match matching_token.kind {
                     TokenKind::Word => matching_token.name.clone(),
                     TokenKind::String => word.to_owned(),
+                    // Pass the rest of the line as typed, so that it can be
+                    // split into words again without losing any quoting.
+                    TokenKind::Line => {
+                        let start = words[index].start;
+                        let end = words.last().unwrap().end;
+                        line[start..end].to_owned()
+                    }
                 };
                 args.push_back((argument_name.clone(), value));
             }
 
             // Update current token ID and proceed to the next word.
             curr_token_id = matching_token_id;
+
+            // The rest of the line was consumed.
+            if matching_token.kind == TokenKind::Line {
+                break;
+            }
         }
     }
 
@@ -179,10 +292,21 @@ pub fn parse_command(
     // Back-tracking: check if the command is present in upper CLI nodes.
     let mut token_id_child = wd_token_id;
     for token_id in wd_token_id.ancestors(&commands.arena) {
-        // Update CLI node when traversing a YANG list.
-        if let Some(token) = commands.get_opt_token(token_id)
-            && token.node_update
+        // Leave the CLI nodes (list entries or containers) located below
+        // this token.
+        while session
+            .mode()
+            .as_configure()
+            .is_some_and(|nodes| !nodes.is_empty())
         {
+            let node_token_id = session.mode().token(commands);
+            if node_token_id == token_id
+                || !node_token_id
+                    .ancestors(&commands.arena)
+                    .any(|ancestor| ancestor == token_id)
+            {
+                break;
+            }
             session.mode_config_exit();
         }
         // Ignore list keys that can match on everything.
@@ -209,3 +333,92 @@ pub fn parse_command(
     session.mode_set(orig_mode);
     orig_ret
 }
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        split_words(line).unwrap()
+    }
+
+    #[test]
+    fn lex_positions() {
+        let words = lex("  foo \"bar baz\"  qux").unwrap();
+        let words = words
+            .iter()
+            .map(|word| (word.value.as_str(), word.start, word.end))
+            .collect::<Vec<_>>();
+        assert_eq!(words, [("foo", 2, 5), ("bar baz", 6, 15), ("qux", 17, 20)]);
+    }
+
+    #[test]
+    fn lex_quotes() {
+        assert_eq!(words("a 'b c' \"d e\""), ["a", "b c", "d e"]);
+        assert_eq!(words("pre'fix'\"suffix\""), ["prefixsuffix"]);
+        assert_eq!(words("'' \"\""), ["", ""]);
+        assert_eq!(words("'it''s'"), ["its"]);
+        assert_eq!(words("\"it's\" 'say \"hi\"'"), ["it's", "say \"hi\""]);
+    }
+
+    #[test]
+    fn lex_escapes() {
+        assert_eq!(words(r"a\ b"), ["a b"]);
+        assert_eq!(words(r#""a\"b\\c""#), [r#"a"b\c"#]);
+        assert_eq!(words(r"'a\b'"), [r"a\b"]);
+        assert_eq!(words(r"\!foo"), ["!foo"]);
+        assert_eq!(words(r"foo\"), [r"foo\"]);
+    }
+
+    #[test]
+    fn lex_comments() {
+        assert_eq!(words("! comment"), Vec::<String>::new());
+        assert_eq!(words("foo ! bar"), ["foo"]);
+        assert_eq!(words("foo!bar"), ["foo!bar"]);
+        assert_eq!(words("'! quoted'"), ["! quoted"]);
+    }
+
+    #[test]
+    fn lex_unterminated_quote() {
+        for line in ["'foo", "\"foo", "foo \"bar", r#""foo\"#, r#""foo\""#] {
+            assert!(
+                matches!(lex(line), Err(ParserError::UnterminatedQuote)),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn quote_word_round_trip() {
+        for word in [
+            "foo",
+            "",
+            "foo bar",
+            "!foo",
+            "foo!",
+            "it's",
+            "say \"hi\"",
+            r"back\slash",
+            "tab\there",
+            r#"all ' " \ !"#,
+        ] {
+            assert_eq!(words(&quote_word(word)), [word], "{}", word);
+        }
+        assert_eq!(quote_word("foo"), "foo");
+        assert_eq!(quote_word("foo bar"), "\"foo bar\"");
+    }
+
+    #[test]
+    fn normalize_input_line_comments() {
+        assert_eq!(normalize_input_line("   "), None);
+        assert_eq!(normalize_input_line("  ! comment"), None);
+        assert_eq!(
+            normalize_input_line(" foo ! bar "),
+            Some("foo ! bar".to_owned())
+        );
+        assert_eq!(normalize_input_line("'foo"), Some("'foo".to_owned()));
+    }
+}
@@ -4,31 +4,101 @@
 // SPDX-License-Identifier: MIT
 //
 
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::fs::{DirBuilder, File, TryLockError};
+use std::os::unix::fs::DirBuilderExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
 use derive_new::new;
 use enum_as_inner::EnumAsInner;
 use indextree::NodeId;
+use yang4::context::Context;
 use yang4::data::{
-    Data, DataFormat, DataParserFlags, DataTree, DataValidationFlags,
+    Data, DataDiff, DataDiffFlags, DataFormat, DataNodeRef, DataParserFlags,
+    DataPrinterFlags, DataTree, DataValidationFlags,
 };
-use yang4::schema::{SchemaNode, SchemaNodeKind};
+use yang4::ffi;
+use yang4::schema::{DataValueType, SchemaNode, SchemaNodeKind};
 
-use crate::error::Error;
-use crate::grpc::{GrpcClient, proto};
+use crate::cli_path::{self, CliPath};
+use crate::error::{Error, ValidationError};
+use crate::grpc::{ConnectOptions, GrpcClient, proto};
 use crate::parser::ParsedArgs;
 use crate::token::Commands;
 use crate::{YANG_CTX, token_yang};
 
 static DEFAULT_HOSTNAME: &str = "holo";
+static DEFAULT_CONNECTION: &str = "default";
 
 #[derive(Debug)]
 pub struct Session {
     hostname: String,
     prompt: String,
     use_pager: bool,
+    commit_preview: bool,
+    interactive: bool,
+    mode: CommandMode,
+    running: DataTree<'static>,
+    running_transaction: Option<u32>,
+    candidate: Option<DataTree<'static>>,
+    candidate_file: Option<String>,
+    autosave_lock: Option<File>,
+    exclusive: bool,
+    grpc_client: Option<GrpcClient>,
+    datastore: Option<String>,
+    modules_dir: Option<String>,
+    connection: String,
+    address: Option<String>,
+    yang_ctx: &'static Context,
+    yang_ctx_changed: bool,
+    connections: BTreeMap<String, Connection>,
+    connect_options: Option<ConnectOptions>,
+    capture: Option<Vec<CapturedOutput>>,
+}
+
+// Command output captured instead of being displayed.
+#[derive(Debug)]
+pub enum CapturedOutput {
+    Text(String),
+    Table {
+        titles: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+// Connection to a holod instance that isn't the active one, along with its
+// configuration session state.
+#[derive(Debug)]
+struct Connection {
+    hostname: String,
     mode: CommandMode,
     running: DataTree<'static>,
+    running_transaction: Option<u32>,
     candidate: Option<DataTree<'static>>,
+    candidate_file: Option<String>,
+    autosave_lock: Option<File>,
+    exclusive: bool,
     grpc_client: GrpcClient,
+    address: String,
+    yang_ctx: &'static Context,
+}
+
+#[derive(Debug)]
+pub struct ConnectionInfo<'a> {
+    pub name: &'a str,
+    pub address: &'a str,
+    pub hostname: &'a str,
+    pub connected: bool,
+    pub active: bool,
+}
+
+// Running configuration committed by another session.
+#[derive(Debug)]
+pub struct RunningUpdate {
+    running: DataTree<'static>,
+    transaction: Option<u32>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, EnumAsInner)]
@@ -53,44 +123,97 @@ pub enum ConfigurationType {
 // ===== impl Session =====
 
 impl Session {
-    pub fn new(use_pager: bool, mut grpc_client: GrpcClient) -> Session {
+    pub fn new(
+        use_pager: bool,
+        address: &str,
+        mut grpc_client: GrpcClient,
+        connect_options: ConnectOptions,
+    ) -> Result<Session, Error> {
         let yang_ctx = YANG_CTX.get().unwrap();
-        let data_format = DataFormat::LYB;
-        let running = grpc_client
-            .get(
-                proto::get_request::DataType::Config,
-                data_format,
-                false,
-                None,
-            )
-            .unwrap();
-        let running = DataTree::parse_string(
+        let running_transaction = fetch_transaction(&mut grpc_client);
+        let running = fetch_running(&mut grpc_client, yang_ctx)?;
+
+        Ok(Session {
+            hostname: DEFAULT_HOSTNAME.to_owned(),
+            prompt: String::new(),
+            use_pager,
+            commit_preview: false,
+            interactive: false,
+            mode: CommandMode::Operational,
+            running,
+            running_transaction,
+            candidate: None,
+            candidate_file: None,
+            autosave_lock: None,
+            exclusive: false,
+            grpc_client: Some(grpc_client),
+            datastore: None,
+            modules_dir: None,
+            connection: DEFAULT_CONNECTION.to_owned(),
+            address: Some(address.to_owned()),
             yang_ctx,
-            running.as_bytes().unwrap(),
-            data_format,
-            DataParserFlags::empty(),
-            DataValidationFlags::PRESENT | DataValidationFlags::NO_STATE,
-        )
-        .expect("Failed to parse data tree");
+            yang_ctx_changed: false,
+            connections: Default::default(),
+            connect_options: Some(connect_options),
+            capture: None,
+        })
+    }
 
-        Session {
+    pub fn new_offline(
+        use_pager: bool,
+        datastore: Option<String>,
+        modules_dir: &str,
+    ) -> Result<Session, Error> {
+        let yang_ctx = YANG_CTX.get().unwrap();
+
+        // Load the running configuration from the local datastore, if it
+        // exists.
+        let running = match &datastore {
+            Some(path) if std::path::Path::new(path).exists() => {
+                let data = std::fs::read_to_string(path).map_err(|error| {
+                    Error::Datastore(format!("{}: {}", path, error))
+                })?;
+                DataTree::parse_string(
+                    yang_ctx,
+                    data,
+                    datastore_format(path),
+                    DataParserFlags::empty(),
+                    DataValidationFlags::PRESENT
+                        | DataValidationFlags::NO_STATE,
+                )
+                .map_err(Error::ValidateConfig)?
+            }
+            _ => DataTree::new(yang_ctx),
+        };
+
+        Ok(Session {
             hostname: DEFAULT_HOSTNAME.to_owned(),
             prompt: String::new(),
             use_pager,
+            commit_preview: false,
+            interactive: false,
             mode: CommandMode::Operational,
             running,
+            running_transaction: None,
             candidate: None,
-            grpc_client,
-        }
+            candidate_file: None,
+            autosave_lock: None,
+            exclusive: false,
+            grpc_client: None,
+            datastore,
+            modules_dir: Some(modules_dir.to_owned()),
+            connection: DEFAULT_CONNECTION.to_owned(),
+            address: None,
+            yang_ctx,
+            yang_ctx_changed: false,
+            connections: Default::default(),
+            connect_options: None,
+            capture: None,
+        })
     }
 
     pub fn update_hostname(&mut self) {
-        self.hostname = self
-            .running
-            .find_path("/ietf-system:system/ietf-system:hostname")
-            .ok()
-            .and_then(|dnode| dnode.value_canonical())
-            .unwrap_or(DEFAULT_HOSTNAME.to_owned());
+        self.hostname = running_hostname(&self.running);
         self.update_prompt();
     }
 
@@ -102,15 +225,125 @@ impl Session {
         self.use_pager
     }
 
+    pub fn commit_preview(&self) -> bool {
+        self.commit_preview
+    }
+
+    pub fn commit_preview_set(&mut self, commit_preview: bool) {
+        self.commit_preview = commit_preview;
+    }
+
+    // Returns whether commands are entered by a user at a terminal, who can
+    // answer questions.
+    pub fn interactive(&self) -> bool {
+        self.interactive
+    }
+
+    pub fn interactive_set(&mut self, interactive: bool) {
+        self.interactive = interactive;
+    }
+
+    // Starts capturing the output of commands instead of displaying it.
+    pub fn capture_start(&mut self) {
+        self.capture = Some(Vec::new());
+    }
+
+    // Stops capturing the output of commands, returning what was captured.
+    pub fn capture_stop(&mut self) -> Vec<CapturedOutput> {
+        self.capture.take().unwrap_or_default()
+    }
+
+    // Returns the captured output, if the output of commands is being
+    // captured.
+    pub fn capture_mut(&mut self) -> Option<&mut Vec<CapturedOutput>> {
+        self.capture.as_mut()
+    }
+
+    // Displays the given text, or captures it if the output of commands is
+    // being captured.
+    pub fn output(&mut self, text: &str) {
+        match &mut self.capture {
+            Some(capture) => {
+                capture.push(CapturedOutput::Text(text.to_owned()))
+            }
+            None => print!("{}", text),
+        }
+    }
+
+    // Displays the given line, or captures it if the output of commands is
+    // being captured.
+    pub fn output_line(&mut self, line: &str) {
+        self.output(&format!("{}\n", line));
+    }
+
+    fn grpc_client(&mut self) -> Result<&mut GrpcClient, Error> {
+        self.grpc_client.as_mut().ok_or(Error::Offline)
+    }
+
+    // Updates the session after a request to holod.
+    //
+    // If the connection was re-established in the meantime, holod might have
+    // been restarted or reconfigured, so the running configuration is fetched
+    // again and checked against the one the candidate configuration is based
+    // on.
+    fn update_connection(&mut self) {
+        let reconnected = self
+            .grpc_client
+            .as_mut()
+            .is_some_and(|grpc_client| grpc_client.take_reconnected());
+        if reconnected {
+            self.output_line("% connection to holod re-established");
+            let grpc_client = self.grpc_client.as_mut().unwrap();
+            let transaction = fetch_transaction(grpc_client);
+            match fetch_running(grpc_client, self.yang_ctx) {
+                Ok(running) => {
+                    let changed = self
+                        .running
+                        .diff(&running, DataDiffFlags::empty())
+                        .map(|diff| diff.iter().next().is_some())
+                        .unwrap_or(true);
+                    if changed && self.candidate.is_some() {
+                        self.output_line(
+                            "% warning: running configuration changed while \
+                             disconnected, the candidate configuration is \
+                             based on an outdated running configuration",
+                        );
+                    }
+                    self.running = running;
+                    self.running_transaction = transaction;
+                    self.update_hostname();
+                }
+                Err(error) => {
+                    self.output_line(&format!(
+                        "% failed to fetch running configuration: {}",
+                        error
+                    ));
+                }
+            }
+        }
+
+        self.update_prompt();
+    }
+
     fn update_prompt(&mut self) {
+        let mut hostname = self.hostname.clone();
+        if let Some(grpc_client) = &self.grpc_client
+            && !grpc_client.is_connected()
+        {
+            hostname += "(disconnected)";
+        }
+        if !self.connections.is_empty() {
+            hostname = format!("{}:{}", self.connection, hostname);
+        }
+
         self.prompt = match &self.mode {
-            CommandMode::Operational => self.hostname.clone(),
+            CommandMode::Operational => hostname,
             CommandMode::Configure { nodes } => {
                 let path = match nodes.last() {
                     Some(node) => &node.cli_path,
                     None => "",
                 };
-                format!("{}(config{})", self.hostname, path)
+                format!("{}(config{})", hostname, path)
             }
         }
     }
@@ -124,17 +357,220 @@ impl Session {
             return;
         }
 
-        // Create/delete candidate configuration if necessary.
+        // Create/delete candidate configuration if necessary. The candidate
+        // configuration is based on the latest running configuration, which
+        // might have been changed by other sessions in the meantime.
         if mode.is_configure() && self.mode.is_operational() {
+            self.running_refresh();
             self.candidate = Some(self.running.duplicate().unwrap());
         } else if mode.is_operational() && self.mode.is_configure() {
+            // The autosaved candidate configuration is no longer needed.
+            if let Some(path) = self.candidate_file.take() {
+                let _ = std::fs::remove_file(&path);
+                let _ = std::fs::remove_file(lock_path(&path));
+            }
             self.candidate = None;
+            self.autosave_lock = None;
+            self.exclusive = false;
         }
 
         self.mode = mode;
         self.update_prompt();
     }
 
+    // Starts autosaving the candidate configuration, so that it can be resumed
+    // after a crash.
+    pub fn candidate_autosave_start(&mut self) {
+        let path = format!(
+            "{}/{}.{}.json",
+            autosave_dir(),
+            self.candidate_key(),
+            std::process::id()
+        );
+
+        // The lock is held while this session exists, telling other sessions
+        // that the autosaved file isn't abandoned.
+        match lock_file(&path).and_then(|file| {
+            file.try_lock().map_err(std::io::Error::from)?;
+            Ok(file)
+        }) {
+            Ok(file) => self.autosave_lock = Some(file),
+            Err(error) => self.output_line(&format!(
+                "% failed to lock autosaved candidate: {}",
+                error
+            )),
+        }
+        self.candidate_file = Some(path);
+    }
+
+    // Returns whether the candidate configuration differs from the running
+    // configuration.
+    pub fn candidate_changed(&self) -> bool {
+        let Some(candidate) = &self.candidate else {
+            return false;
+        };
+        self.running
+            .diff(candidate, DataDiffFlags::DEFAULTS)
+            .map(|diff| diff.iter().next().is_some())
+            .unwrap_or(true)
+    }
+
+    // Looks for a candidate configuration autosaved by a session that no
+    // longer exists, which is the case when nobody holds its lock.
+    pub fn candidate_autosaved(&self) -> Option<String> {
+        let prefix = format!("{}.", self.candidate_key());
+        std::fs::read_dir(autosave_dir())
+            .ok()?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.starts_with(&prefix) && name.ends_with(".json"))
+            .map(|name| format!("{}/{}", autosave_dir(), name))
+            .filter(|path| Some(path) != self.candidate_file.as_ref())
+            .find(|path| {
+                lock_file(path).is_ok_and(|file| {
+                    !matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+                })
+            })
+    }
+
+    // Resumes the given autosaved candidate configuration, applying its
+    // changes on top of the running configuration.
+    pub fn candidate_resume(&mut self, path: &str) -> Result<(), Error> {
+        let candidate = candidate_load(path, &self.running, self.yang_ctx)?;
+        self.candidate = Some(candidate);
+        self.candidate_save();
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(lock_path(path));
+        Ok(())
+    }
+
+    // Saves the changes made to the candidate configuration, removing the file
+    // when there are none.
+    fn candidate_save(&mut self) {
+        let (Some(path), Some(candidate)) =
+            (&self.candidate_file, &self.candidate)
+        else {
+            return;
+        };
+
+        if let Err(error) = candidate_store(path, &self.running, candidate) {
+            self.output_line(&format!("% failed to save candidate: {}", error));
+        }
+    }
+
+    // Returns a name identifying the candidate configuration's target.
+    fn candidate_key(&self) -> String {
+        let target = match (&self.address, &self.datastore) {
+            (Some(address), _) => address.as_str(),
+            (None, Some(datastore)) => datastore.as_str(),
+            (None, None) => "offline",
+        };
+        target
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || "-_".contains(c) {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    pub fn exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    // Marks the configuration session as exclusive.
+    //
+    // holod doesn't support configuration locks, so this is enforced when
+    // committing: the commit is refused if another session committed in the
+    // meantime, instead of offering to rebase the candidate configuration.
+    pub fn exclusive_set(&mut self) {
+        self.exclusive = true;
+    }
+
+    // Fetches the running configuration again, keeping the cached one if that
+    // fails.
+    fn running_refresh(&mut self) {
+        let Some(grpc_client) = &mut self.grpc_client else {
+            return;
+        };
+
+        let transaction = fetch_transaction(grpc_client);
+        match fetch_running(grpc_client, self.yang_ctx) {
+            Ok(running) => {
+                self.running = running;
+                self.running_transaction = transaction;
+                self.update_hostname();
+            }
+            Err(error) => {
+                self.output_line(&format!(
+                    "% failed to fetch running configuration: {}",
+                    error
+                ));
+            }
+        }
+        self.update_connection();
+    }
+
+    // Checks whether another session committed since the running
+    // configuration was fetched, returning the new running configuration if
+    // so.
+    //
+    // The ID of the last transaction in the rollback log is checked first, so
+    // that the running configuration is only fetched again when it might have
+    // changed.
+    pub fn running_check(&mut self) -> Result<Option<RunningUpdate>, Error> {
+        let Some(grpc_client) = &mut self.grpc_client else {
+            return Ok(None);
+        };
+
+        let transaction = fetch_transaction(grpc_client);
+        if transaction.is_some() && transaction == self.running_transaction {
+            return Ok(None);
+        }
+        let result = fetch_running(grpc_client, self.yang_ctx);
+        self.update_connection();
+        let running = result?;
+        let changed = self
+            .running
+            .diff(&running, DataDiffFlags::empty())
+            .map(|diff| diff.iter().next().is_some())
+            .unwrap_or(true);
+        if !changed {
+            self.running_transaction = transaction;
+            return Ok(None);
+        }
+
+        Ok(Some(RunningUpdate {
+            running,
+            transaction,
+        }))
+    }
+
+    // Applies the changes made to the candidate configuration on top of the
+    // new running configuration.
+    pub fn candidate_rebase(
+        &mut self,
+        update: RunningUpdate,
+    ) -> Result<(), Error> {
+        let candidate = self.candidate.as_ref().unwrap();
+        let diff = self
+            .running
+            .diff(candidate, DataDiffFlags::DEFAULTS)
+            .map_err(Error::Rebase)?;
+        let mut candidate = update.running.duplicate().unwrap();
+        candidate.diff_apply(&diff).map_err(Error::Rebase)?;
+
+        self.candidate = Some(candidate);
+        self.running = update.running;
+        self.running_transaction = update.transaction;
+        self.update_hostname();
+        self.candidate_save();
+        Ok(())
+    }
+
     fn mode_config_enter(&mut self, node: CommandNode) {
         let nodes = self.mode.as_configure_mut().unwrap();
         nodes.push(node);
@@ -155,6 +591,121 @@ impl Session {
         self.update_prompt();
     }
 
+    // Moves up the given number of CLI nodes, stopping at the top level.
+    pub fn mode_config_up(&mut self, count: usize) {
+        let nodes = self.mode.as_configure_mut().unwrap();
+        nodes.truncate(nodes.len().saturating_sub(count));
+        self.update_prompt();
+    }
+
+    // Moves to the configuration node at the given CLI path, which is absolute
+    // when it starts with "/" and relative to the current CLI node otherwise.
+    //
+    // Every container and list entry along the path becomes a CLI node, so
+    // that "up" walks back through them one level at a time.
+    pub fn mode_config_edit(&mut self, words: &[String]) -> Result<(), Error> {
+        let mut nodes = self.mode.as_configure().unwrap().clone();
+        let mut words = words.to_vec();
+        if words.first().is_some_and(|word| word.starts_with('/')) {
+            nodes.clear();
+            let word = words.remove(0);
+            let word = word.trim_start_matches('/');
+            if !word.is_empty() {
+                words.insert(0, word.to_owned());
+            }
+        }
+
+        let base_path = nodes.last().map(|node| node.data_path.clone());
+        let base = match &base_path {
+            Some(data_path) => {
+                let snode = self
+                    .yang_ctx
+                    .find_path(data_path)
+                    .map_err(Error::EditConfig)?;
+                Some((snode, data_path.as_str()))
+            }
+            None => None,
+        };
+        let mut cli_path: Option<CliPath<'_>> = None;
+        let mut words = words.as_slice();
+        while let Some(word) = words.first() {
+            let Some((next, rest)) = cli_path::resolve_next(
+                self.yang_ctx,
+                cli_path.as_ref(),
+                base.as_ref(),
+                words,
+            ) else {
+                return Err(Error::InvalidPath(format!(
+                    "unknown path element: {}",
+                    word
+                )));
+            };
+
+            // Get the token of the new CLI node.
+            let token_snode = match next.snode.kind() {
+                SchemaNodeKind::Container => next.snode.clone(),
+                SchemaNodeKind::List if !next.wildcard => {
+                    next.snode.list_keys().last().unwrap()
+                }
+                SchemaNodeKind::List => {
+                    return Err(Error::InvalidPath(format!(
+                        "missing keys for list: {}",
+                        word
+                    )));
+                }
+                _ => {
+                    return Err(Error::InvalidPath(format!(
+                        "not a container or list: {}",
+                        word
+                    )));
+                }
+            };
+            if token_snode.get_private().is_none() {
+                return Err(Error::InvalidPath(format!(
+                    "unsupported path element: {}",
+                    word
+                )));
+            }
+            let token_id = token_yang::snode_get_token_id(&token_snode);
+
+            // Build the CLI path of the new CLI node.
+            let mut node_cli_path = nodes
+                .last()
+                .map(|node| node.cli_path.clone())
+                .unwrap_or_default();
+            let keys = &words[1..words.len() - rest.len()];
+            if keys.is_empty() {
+                node_cli_path += &format!("/{}", word);
+            } else {
+                node_cli_path += &format!("/{}[{}]", word, keys.join(" "));
+            }
+
+            nodes.push(CommandNode::new(
+                token_id,
+                node_cli_path,
+                next.data_path.clone(),
+            ));
+            words = rest;
+            cli_path = Some(next);
+        }
+
+        // Create the list entry being edited, if any, just like when entering
+        // it implicitly.
+        if let Some(cli_path) = cli_path
+            && cli_path.snode.kind() == SchemaNodeKind::List
+        {
+            let candidate = self.candidate.as_mut().unwrap();
+            candidate
+                .new_path(&cli_path.data_path, None, false)
+                .map_err(Error::EditConfig)?;
+            self.candidate_save();
+        }
+
+        *self.mode.as_configure_mut().unwrap() = nodes;
+        self.update_prompt();
+        Ok(())
+    }
+
     pub fn edit_candidate(
         &mut self,
         negate: bool,
@@ -174,18 +725,19 @@ impl Session {
         }
 
         // Iterate over all schema nodes starting from the root.
-        let mut skip = self.mode.as_configure().unwrap().len();
+        let mut skip = self
+            .mode
+            .data_path()
+            .and_then(|data_path| self.yang_ctx.find_path(&data_path).ok());
         for snode in snodes.iter().filter(|snode| !snode.is_schema_only()).rev()
         {
-            // Ignore schema nodes above the current CLI node.
-            if skip > 0 {
-                if snode.kind() == SchemaNodeKind::List || snode.is_list_key() {
-                    skip -= 1;
-                    continue;
-                }
-                if skip > 0 {
-                    continue;
+            // Ignore schema nodes above (and including) the current CLI node,
+            // which is either a list or a container.
+            if let Some(cli_snode) = &skip {
+                if snode == cli_snode {
+                    skip = None;
                 }
+                continue;
             }
 
             // Update data path.
@@ -193,7 +745,7 @@ impl Session {
             let mut list_keys = ParsedArgs::new();
             for snode in snode.list_keys() {
                 let arg = args.pop_front().unwrap();
-                path += &format!("[{}='{}']", snode.name(), arg.1);
+                path += &cli_path::key_predicate(snode.name(), &arg.1);
                 list_keys.push_back(arg);
             }
 
@@ -240,12 +792,376 @@ impl Session {
         } else {
             candidate.new_path(&path, value.as_deref(), false)?;
         }
+        self.candidate_save();
 
         Ok(())
     }
 
+    // Resolves a CLI path relative to the current configuration node.
+    pub fn cli_path_resolve<'a>(
+        &self,
+        words: &'a [String],
+    ) -> (Option<CliPath<'static>>, &'a [String]) {
+        let data_path = self.mode.data_path();
+        let base = data_path.as_deref().and_then(|data_path| {
+            let snode = self.yang_ctx.find_path(data_path).ok()?;
+            Some((snode, data_path))
+        });
+        cli_path::resolve(self.yang_ctx, base, words)
+    }
+
+    // Copies a list entry, along with its descendants, under new keys.
+    //
+    // The words consist of the CLI path of the list entry followed by the new
+    // key values.
+    pub fn candidate_copy(&mut self, words: &[String]) -> Result<(), Error> {
+        let (cli_path, keys) = self.list_entry_resolve(words)?;
+
+        // Duplicate the list entry (along with its parents) from a copy of the
+        // candidate configuration, as it can't be merged into the tree it
+        // belongs to.
+        let candidate = self.candidate.as_mut().unwrap();
+        let tree = candidate.duplicate().unwrap();
+        let dup = tree
+            .find_path(&cli_path.data_path)
+            .and_then(|dnode| dnode.duplicate(true))
+            .map_err(Error::EditConfig)?;
+        let dup_dnode = dup.find_path(&cli_path.data_path).unwrap();
+        for (key, value) in dup_dnode.list_keys().zip(&keys) {
+            change_term(self.yang_ctx, &key, value)?;
+        }
+        candidate.merge(&dup).map_err(Error::EditConfig)?;
+
+        self.candidate_save();
+        Ok(())
+    }
+
+    // Changes the keys of a list entry, optionally updating the leafrefs that
+    // point to the old keys.
+    //
+    // Returns the number of updated leafrefs.
+    pub fn candidate_rename(
+        &mut self,
+        words: &[String],
+        update_refs: bool,
+    ) -> Result<usize, Error> {
+        let (cli_path, keys) = self.list_entry_resolve(words)?;
+        if self
+            .mode
+            .data_path()
+            .is_some_and(|data_path| data_path.starts_with(&cli_path.data_path))
+        {
+            return Err(Error::InvalidPath(
+                "can't rename the list entry being edited".to_owned(),
+            ));
+        }
+
+        let candidate = self.candidate.as_ref().unwrap();
+        let dnode = candidate.find_path(&cli_path.data_path).unwrap();
+
+        // Find the leafrefs pointing to the keys that change.
+        let mut refs = vec![];
+        if update_refs {
+            for (key, value) in dnode.list_keys().zip(&keys) {
+                let old_value = key.value_canonical().unwrap_or_default();
+                if old_value == *value {
+                    continue;
+                }
+                let key_snode = key.schema();
+                refs.extend(
+                    candidate
+                        .traverse()
+                        .filter(|dnode| {
+                            dnode.value_canonical().as_deref()
+                                == Some(old_value.as_str())
+                        })
+                        .filter(|dnode| {
+                            leafref_points_to(&dnode.schema(), &key_snode)
+                        })
+                        .map(|dnode| (dnode.path(), value.clone())),
+                );
+            }
+        }
+
+        // Change the keys first, and then the leafrefs.
+        for (key, value) in dnode.list_keys().zip(&keys) {
+            change_term(self.yang_ctx, &key, value)?;
+        }
+        for (path, value) in &refs {
+            let dnode = candidate.find_path(path).map_err(Error::EditConfig)?;
+            change_term(self.yang_ctx, &dnode, value)?;
+        }
+
+        self.candidate_save();
+        Ok(refs.len())
+    }
+
+    // Moves an entry of a user-ordered list or leaf-list, creating it if
+    // necessary.
+    //
+    // The words consist of the CLI path of the entry followed by its new
+    // position: "before" or "after" followed by the keys (or value) of another
+    // entry, "first" or "last".
+    pub fn candidate_insert(&mut self, words: &[String]) -> Result<(), Error> {
+        let (cli_path, mut words) = self.cli_path_resolve(words);
+        let cli_path = match cli_path {
+            Some(cli_path)
+                if matches!(
+                    cli_path.snode.kind(),
+                    SchemaNodeKind::List | SchemaNodeKind::LeafList
+                ) && cli_path.snode.is_user_ordered()
+                    && !cli_path.wildcard =>
+            {
+                cli_path
+            }
+            _ => {
+                return Err(Error::InvalidPath(
+                    "expected an entry of a user-ordered list or leaf-list"
+                        .to_owned(),
+                ));
+            }
+        };
+        if cli_path.parent_path.is_empty() {
+            return Err(Error::InvalidPath(
+                "top-level lists can't be reordered".to_owned(),
+            ));
+        }
+
+        // Leaf-list entries are identified by their value.
+        let leaf_list = cli_path.snode.kind() == SchemaNodeKind::LeafList;
+        let mut path = cli_path.data_path.clone();
+        if leaf_list {
+            let Some((value, rest)) = words.split_first() else {
+                return Err(Error::InvalidPath(
+                    "missing leaf-list value".to_owned(),
+                ));
+            };
+            path += &cli_path::key_predicate(".", value);
+            words = rest;
+        }
+
+        // Parse the new position.
+        let (position, words) = words
+            .split_first()
+            .ok_or_else(|| Error::InvalidPath("missing position".to_owned()))?;
+        let other = match position.as_str() {
+            "before" | "after" => {
+                let mut other = format!(
+                    "{}/{}:{}",
+                    cli_path.parent_path,
+                    cli_path.snode.module().name(),
+                    cli_path.snode.name()
+                );
+                let keys = if leaf_list {
+                    vec![".".to_owned()]
+                } else {
+                    cli_path
+                        .snode
+                        .list_keys()
+                        .map(|snode| snode.name().to_owned())
+                        .collect()
+                };
+                if words.len() != keys.len() {
+                    return Err(Error::InvalidPath(format!(
+                        "expected {} key value(s) after {}",
+                        keys.len(),
+                        position
+                    )));
+                }
+                for (key, value) in keys.iter().zip(words) {
+                    other += &cli_path::key_predicate(key, value);
+                }
+                Some(other)
+            }
+            "first" | "last" if words.is_empty() => None,
+            _ => {
+                return Err(Error::InvalidPath(
+                    "expected before, after, first or last".to_owned(),
+                ));
+            }
+        };
+
+        // Create the entry if it doesn't exist yet.
+        let candidate = self.candidate.as_mut().unwrap();
+        if candidate.find_path(&path).is_err() {
+            candidate
+                .new_path(&path, None, false)
+                .map_err(Error::EditConfig)?;
+        }
+        let dnode = candidate.find_path(&path).map_err(Error::EditConfig)?;
+
+        // Find the entry to insert the entry before or after.
+        let sibling = match &other {
+            Some(other) => candidate.find_path(other).map_err(|_| {
+                Error::InvalidPath(format!("entry not found: {}", other))
+            })?,
+            None => {
+                let snode = dnode.schema().as_raw();
+                let mut entries = dnode
+                    .ancestors()
+                    .next()
+                    .unwrap()
+                    .children()
+                    .filter(|dnode| dnode.schema().as_raw() == snode);
+                if position == "first" {
+                    entries.next().unwrap()
+                } else {
+                    entries.last().unwrap()
+                }
+            }
+        };
+
+        // The entry might have been created above, so the candidate is saved
+        // even if it's already in place.
+        if sibling != dnode {
+            let ret = unsafe {
+                match position.as_str() {
+                    "before" | "first" => {
+                        ffi::lyd_insert_before(sibling.as_raw(), dnode.as_raw())
+                    }
+                    _ => {
+                        ffi::lyd_insert_after(sibling.as_raw(), dnode.as_raw())
+                    }
+                }
+            };
+            if ret != ffi::LY_ERR::LY_SUCCESS {
+                return Err(Error::EditConfig(yang4::Error::new(
+                    self.yang_ctx,
+                )));
+            }
+        }
+
+        self.candidate_save();
+        Ok(())
+    }
+
+    // Finds the candidate configuration nodes matching the given CLI path,
+    // which might be partial or contain "*" wildcards in place of list keys and
+    // leaf-list values.
+    //
+    // Returns the data paths of the matching nodes, along with the number of
+    // nodes in their subtrees.
+    pub fn candidate_find(
+        &mut self,
+        words: &[String],
+    ) -> Result<Vec<(String, usize)>, Error> {
+        let (cli_path, words) = self.cli_path_resolve(words);
+        let Some(cli_path) = cli_path else {
+            return Err(Error::InvalidPath("no matching node".to_owned()));
+        };
+
+        // Leafs and leaf-lists can be followed by their value.
+        let mut xpath = cli_path.data_path;
+        let words = match words.split_first() {
+            Some((value, words))
+                if matches!(
+                    cli_path.snode.kind(),
+                    SchemaNodeKind::Leaf | SchemaNodeKind::LeafList
+                ) =>
+            {
+                if value != "*" {
+                    xpath += &cli_path::key_predicate(".", value);
+                }
+                words
+            }
+            _ => words,
+        };
+        if let Some(word) = words.first() {
+            return Err(Error::InvalidPath(format!(
+                "unknown path element: {}",
+                word
+            )));
+        }
+
+        let candidate = self.candidate.as_ref().unwrap();
+        let paths = candidate
+            .find_xpath(&xpath)
+            .map_err(Error::EditConfig)?
+            .map(|dnode| (dnode.path(), dnode.traverse().count()))
+            .collect();
+        Ok(paths)
+    }
+
+    // Removes the given nodes, along with their descendants, from the
+    // candidate configuration.
+    pub fn candidate_delete(&mut self, paths: &[String]) -> Result<(), Error> {
+        let candidate = self.candidate.as_mut().unwrap();
+        for path in paths {
+            // The node might have been removed along with an ancestor.
+            if candidate.find_path(path).is_ok() {
+                candidate.remove(path).map_err(Error::EditConfig)?;
+            }
+        }
+
+        self.candidate_save();
+        Ok(())
+    }
+
+    // Resolves the CLI path of an existing list entry followed by new key
+    // values, checking that no entry with these keys exists yet.
+    fn list_entry_resolve(
+        &mut self,
+        words: &[String],
+    ) -> Result<(CliPath<'static>, Vec<String>), Error> {
+        let (cli_path, keys) = self.cli_path_resolve(words);
+        let cli_path = match cli_path {
+            Some(cli_path)
+                if cli_path.snode.kind() == SchemaNodeKind::List
+                    && !cli_path.wildcard =>
+            {
+                cli_path
+            }
+            _ => {
+                return Err(Error::InvalidPath(
+                    "expected a list entry".to_owned(),
+                ));
+            }
+        };
+        let key_snodes = cli_path.snode.list_keys().collect::<Vec<_>>();
+        if keys.len() != key_snodes.len() {
+            return Err(Error::InvalidPath(format!(
+                "expected {} new key value(s)",
+                key_snodes.len()
+            )));
+        }
+
+        let candidate = self.candidate.as_ref().unwrap();
+        if candidate.find_path(&cli_path.data_path).is_err() {
+            return Err(Error::InvalidPath(format!(
+                "list entry not found: {}",
+                cli_path.data_path
+            )));
+        }
+        let mut new_path = format!(
+            "{}/{}:{}",
+            cli_path.parent_path,
+            cli_path.snode.module().name(),
+            cli_path.snode.name()
+        );
+        for (snode, value) in key_snodes.iter().zip(keys) {
+            new_path += &cli_path::key_predicate(snode.name(), value);
+        }
+        if candidate.find_path(&new_path).is_ok() {
+            return Err(Error::InvalidPath(format!(
+                "list entry already exists: {}",
+                new_path
+            )));
+        }
+
+        Ok((cli_path, keys.to_vec()))
+    }
+
+    // Returns the changes made to the candidate configuration.
+    pub fn candidate_diff(&mut self) -> Result<DataDiff<'static>, Error> {
+        let candidate = self.candidate.as_ref().unwrap();
+        self.running
+            .diff(candidate, DataDiffFlags::empty())
+            .map_err(Error::ValidateConfig)
+    }
+
     pub fn candidate_discard(&mut self) {
         self.candidate = Some(self.running.duplicate().unwrap());
+        self.candidate_save();
     }
 
     pub fn candidate_validate(&mut self) -> Result<(), Error> {
@@ -255,7 +1171,12 @@ impl Session {
         Session::validate_configuration_yang(candidate)?;
 
         // Request the device to do a full configuration validation.
-        self.grpc_client.validate_candidate(candidate)
+        let result = match &mut self.grpc_client {
+            Some(grpc_client) => grpc_client.validate_candidate(candidate),
+            None => Ok(()),
+        };
+        self.update_connection();
+        result
     }
 
     pub fn candidate_commit(
@@ -268,15 +1189,32 @@ impl Session {
         Session::validate_configuration_yang(candidate)?;
 
         // Request the device to validate and commit the candidate
-        // configuration.
-        self.grpc_client
-            .commit_candidate(&self.running, candidate, comment)?;
+        // configuration. In offline mode, save it to the local datastore
+        // instead.
+        let result = match &mut self.grpc_client {
+            Some(grpc_client) => {
+                grpc_client.commit_candidate(&self.running, candidate, comment)
+            }
+            None => match &self.datastore {
+                Some(path) => datastore_save(path, candidate),
+                None => Ok(()),
+            },
+        };
+        if let Err(error) = result {
+            self.update_connection();
+            return Err(error);
+        }
 
         // Replace the running configuration with the candidate configuration.
         self.running = candidate.duplicate().unwrap();
+        if let Some(grpc_client) = &mut self.grpc_client {
+            self.running_transaction = fetch_transaction(grpc_client);
+        }
+        self.candidate_save();
 
         // Fetch hostname from running configuration and update the prompt.
         self.update_hostname();
+        self.update_connection();
 
         Ok(())
     }
@@ -284,9 +1222,24 @@ impl Session {
     fn validate_configuration_yang(
         config: &mut DataTree<'static>,
     ) -> Result<(), Error> {
-        config
-            .validate(DataValidationFlags::NO_STATE)
-            .map_err(Error::ValidateConfig)
+        let yang_ctx = YANG_CTX.get().unwrap();
+
+        // Have libyang report all validation errors rather than just the
+        // first one.
+        let mut log_options = ffi::LY_LOSTORE;
+        unsafe { ffi::ly_temp_log_options(&mut log_options) };
+        ValidationError::clear_yang(yang_ctx);
+        let result = config.validate(
+            DataValidationFlags::NO_STATE
+                | DataValidationFlags::from_bits_retain(
+                    ffi::LYD_VALIDATE_MULTI_ERROR,
+                ),
+        );
+        let result = result.map_err(|error| {
+            Error::Validation(ValidationError::from_yang(yang_ctx, error))
+        });
+        unsafe { ffi::ly_temp_log_options(std::ptr::null_mut()) };
+        result
     }
 
     pub fn get_configuration(
@@ -306,15 +1259,206 @@ impl Session {
         with_defaults: bool,
         xpath: Option<String>,
     ) -> Result<proto::data_tree::Data, Error> {
+        let result =
+            self.grpc_client()?
+                .get(data_type, format, with_defaults, xpath);
+        self.update_connection();
+        result
+    }
+
+    pub fn data_format(&self) -> DataFormat {
+        match &self.grpc_client {
+            Some(grpc_client) => grpc_client.data_format(),
+            None => DataFormat::LYB,
+        }
+    }
+
+    pub fn capabilities(&self) -> Option<&proto::CapabilitiesResponse> {
         self.grpc_client
-            .get(data_type, format, with_defaults, xpath)
+            .as_ref()
+            .and_then(|grpc_client| grpc_client.capabilities())
+    }
+
+    // Returns the path of the index of the configuration commands generated
+    // from the active YANG module set, if it can be persisted.
+    pub fn command_index_path(&self) -> Option<String> {
+        let modules_dir = match &self.grpc_client {
+            Some(grpc_client) => grpc_client.modules_cache_dir(),
+            None => self.modules_dir.as_deref(),
+        }?;
+        token_yang::command_index_path(modules_dir, self.yang_ctx)
+    }
+
+    pub fn address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+
+    pub fn set_timeout(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<(), Error> {
+        self.grpc_client()?.set_timeout(timeout);
+        if let Some(connect_options) = &mut self.connect_options {
+            connect_options.timeout = timeout;
+        }
+        Ok(())
+    }
+
+    // Connects to another holod instance and makes it the active connection.
+    pub fn connect(&mut self, name: &str, address: &str) -> Result<(), Error> {
+        let connect_options =
+            self.connect_options.as_ref().ok_or(Error::Offline)?;
+        if name == self.connection || self.connections.contains_key(name) {
+            return Err(Error::Connection(format!(
+                "connection already exists: {}",
+                name
+            )));
+        }
+
+        let (mut grpc_client, yang_ctx) =
+            crate::connect(address, connect_options)
+                .map_err(Error::Connection)?;
+        let running = fetch_running(&mut grpc_client, yang_ctx)?;
+        let running_transaction = fetch_transaction(&mut grpc_client);
+        let mut connection = Connection {
+            hostname: DEFAULT_HOSTNAME.to_owned(),
+            mode: CommandMode::Operational,
+            running,
+            running_transaction,
+            candidate: None,
+            candidate_file: None,
+            autosave_lock: None,
+            exclusive: false,
+            grpc_client,
+            address: address.to_owned(),
+            yang_ctx,
+        };
+        connection.update_hostname();
+        self.connections.insert(name.to_owned(), connection);
+
+        self.switch(name)
+    }
+
+    // Makes the given connection the active one.
+    //
+    // The command mode and the candidate configuration are kept per
+    // connection, so uncommitted changes are still there when switching back.
+    pub fn switch(&mut self, name: &str) -> Result<(), Error> {
+        if name == self.connection {
+            return Ok(());
+        }
+        let Some(connection) = self.connections.remove(name) else {
+            return Err(Error::Connection(format!(
+                "unknown connection: {}",
+                name
+            )));
+        };
+
+        // Save the state of the active connection.
+        let previous = Connection {
+            hostname: std::mem::replace(
+                &mut self.hostname,
+                connection.hostname,
+            ),
+            mode: std::mem::replace(&mut self.mode, connection.mode),
+            running: std::mem::replace(&mut self.running, connection.running),
+            running_transaction: std::mem::replace(
+                &mut self.running_transaction,
+                connection.running_transaction,
+            ),
+            candidate: std::mem::replace(
+                &mut self.candidate,
+                connection.candidate,
+            ),
+            candidate_file: std::mem::replace(
+                &mut self.candidate_file,
+                connection.candidate_file,
+            ),
+            autosave_lock: std::mem::replace(
+                &mut self.autosave_lock,
+                connection.autosave_lock,
+            ),
+            exclusive: std::mem::replace(
+                &mut self.exclusive,
+                connection.exclusive,
+            ),
+            grpc_client: self
+                .grpc_client
+                .replace(connection.grpc_client)
+                .unwrap(),
+            address: self.address.replace(connection.address).unwrap(),
+            yang_ctx: std::mem::replace(
+                &mut self.yang_ctx,
+                connection.yang_ctx,
+            ),
+        };
+        let previous_name =
+            std::mem::replace(&mut self.connection, name.to_owned());
+        self.connections.insert(previous_name, previous);
+
+        // The command tree is regenerated for the new YANG context, so CLI
+        // nodes can't be kept.
+        if let Some(nodes) = self.mode.as_configure_mut() {
+            nodes.clear();
+        }
+
+        // Activate the YANG context of the new connection.
+        YANG_CTX.set(self.yang_ctx);
+        self.yang_ctx_changed = true;
+        self.update_prompt();
+
+        Ok(())
+    }
+
+    // Returns information about all connections, sorted by name.
+    pub fn connections(&self) -> Vec<ConnectionInfo<'_>> {
+        let mut connections = self
+            .connections
+            .iter()
+            .map(|(name, connection)| ConnectionInfo {
+                name,
+                address: &connection.address,
+                hostname: &connection.hostname,
+                connected: connection.grpc_client.is_connected(),
+                active: false,
+            })
+            .collect::<Vec<_>>();
+        if let (Some(address), Some(grpc_client)) =
+            (&self.address, &self.grpc_client)
+        {
+            connections.push(ConnectionInfo {
+                name: &self.connection,
+                address,
+                hostname: &self.hostname,
+                connected: grpc_client.is_connected(),
+                active: true,
+            });
+        }
+        connections.sort_by_key(|connection| connection.name);
+        connections
+    }
+
+    // Returns whether the active YANG context changed since the last call,
+    // clearing the flag.
+    pub fn take_yang_ctx_changed(&mut self) -> bool {
+        std::mem::take(&mut self.yang_ctx_changed)
     }
 
     pub fn execute(
         &mut self,
         data: DataTree<'static>,
     ) -> Result<proto::data_tree::Data, Error> {
-        self.grpc_client.execute(data)
+        let result = self.grpc_client()?.execute(data);
+        self.update_connection();
+        result
+    }
+}
+
+// ===== impl Connection =====
+
+impl Connection {
+    fn update_hostname(&mut self) {
+        self.hostname = running_hostname(&self.running);
     }
 }
 
@@ -349,3 +1493,222 @@ impl CommandMode {
         }
     }
 }
+
+// ===== helper functions =====
+
+// Fetches the hostname from the running configuration.
+fn running_hostname(running: &DataTree<'static>) -> String {
+    running
+        .find_path("/ietf-system:system/ietf-system:hostname")
+        .ok()
+        .and_then(|dnode| dnode.value_canonical())
+        .unwrap_or(DEFAULT_HOSTNAME.to_owned())
+}
+
+// Fetches the ID of the last configuration transaction from holod.
+//
+// Errors are ignored, as the rollback log might be disabled.
+fn fetch_transaction(grpc_client: &mut GrpcClient) -> Option<u32> {
+    grpc_client.last_transaction().ok().flatten()
+}
+
+// Fetches the running configuration from holod.
+fn fetch_running(
+    grpc_client: &mut GrpcClient,
+    yang_ctx: &'static Context,
+) -> Result<DataTree<'static>, Error> {
+    let data_format = grpc_client.data_format();
+    let running = grpc_client.get(
+        proto::get_request::DataType::Config,
+        data_format,
+        false,
+        None,
+    )?;
+    DataTree::parse_string(
+        yang_ctx,
+        running.as_bytes(),
+        data_format,
+        DataParserFlags::empty(),
+        DataValidationFlags::PRESENT | DataValidationFlags::NO_STATE,
+    )
+    .map_err(Error::ValidateConfig)
+}
+
+// Returns the data format of the local datastore based on its file extension.
+fn datastore_format(path: &str) -> DataFormat {
+    match std::path::Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("xml") => DataFormat::XML,
+        _ => DataFormat::JSON,
+    }
+}
+
+// Saves the given configuration to the local datastore.
+fn datastore_save(path: &str, config: &DataTree<'static>) -> Result<(), Error> {
+    let data = config
+        .print_string(datastore_format(path), DataPrinterFlags::WITH_SIBLINGS)
+        .map_err(|error| Error::Datastore(format!("{}: {}", path, error)))?;
+    std::fs::write(path, data)
+        .map_err(|error| Error::Datastore(format!("{}: {}", path, error)))
+}
+
+// Returns the directory where candidate configurations are autosaved.
+fn autosave_dir() -> String {
+    let state_dir =
+        match (std::env::var("XDG_STATE_HOME"), std::env::var("HOME")) {
+            (Ok(dir), _) if !dir.is_empty() => PathBuf::from(dir),
+            (_, Ok(home)) if !home.is_empty() => {
+                PathBuf::from(home).join(".local/state")
+            }
+            _ => std::env::temp_dir(),
+        };
+    state_dir
+        .join("holo-cli/candidates")
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Returns the path of the lock file of a candidate configuration file.
+fn lock_path(path: &str) -> String {
+    format!("{}.lock", path)
+}
+
+// Opens the lock file of a candidate configuration file, creating it (along
+// with its directory) if necessary.
+fn lock_file(path: &str) -> Result<File, std::io::Error> {
+    let path = lock_path(path);
+    if let Some(parent) = std::path::Path::new(&path).parent() {
+        create_private_dir(parent)?;
+    }
+    File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)
+}
+
+// Creates a directory only accessible to the user, since candidate
+// configurations might contain secrets.
+fn create_private_dir(path: &std::path::Path) -> Result<(), std::io::Error> {
+    DirBuilder::new().recursive(true).mode(0o700).create(path)
+}
+
+// Loads the changes saved by `candidate_store`, applying them on top of the
+// running configuration.
+fn candidate_load(
+    path: &str,
+    running: &DataTree<'static>,
+    yang_ctx: &'static Context,
+) -> Result<DataTree<'static>, Error> {
+    let data = std::fs::read(path)
+        .map_err(|error| Error::Datastore(format!("{}: {}", path, error)))?;
+    let diff = DataDiff::parse_string(
+        yang_ctx,
+        data,
+        DataFormat::JSON,
+        DataParserFlags::NO_VALIDATION,
+        DataValidationFlags::empty(),
+    )
+    .map_err(Error::Rebase)?;
+    let mut candidate = running.duplicate().unwrap();
+    candidate.diff_apply(&diff).map_err(Error::Rebase)?;
+    Ok(candidate)
+}
+
+// Saves the changes made to the candidate configuration.
+//
+// Only the changes are saved, rather than the full candidate configuration, so
+// that they can be applied on top of a running configuration changed in the
+// meantime.
+fn candidate_store(
+    path: &str,
+    running: &DataTree<'static>,
+    candidate: &DataTree<'static>,
+) -> Result<(), Error> {
+    let diff = running
+        .diff(candidate, DataDiffFlags::DEFAULTS)
+        .map_err(Error::Rebase)?;
+    if diff.iter().next().is_none() {
+        if std::path::Path::new(path).exists() {
+            std::fs::remove_file(path).map_err(|error| {
+                Error::Datastore(format!("{}: {}", path, error))
+            })?;
+        }
+        return Ok(());
+    }
+
+    let data = diff
+        .print_string(DataFormat::JSON, DataPrinterFlags::WITH_SIBLINGS)
+        .map_err(|error| Error::Datastore(format!("{}: {}", path, error)))?;
+    let path = std::path::Path::new(path);
+    if let Some(parent) = path.parent() {
+        create_private_dir(parent).map_err(|error| {
+            Error::Datastore(format!("{}: {}", parent.display(), error))
+        })?;
+    }
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, data)
+        .and_then(|_| std::fs::rename(&tmp_path, path))
+        .map_err(|error| {
+            Error::Datastore(format!("{}: {}", path.display(), error))
+        })
+}
+
+// Changes the value of a leaf or leaf-list data node.
+//
+// libyang takes care of rehashing and reordering the parent list entry when
+// the leaf is a list key.
+fn change_term(
+    yang_ctx: &'static Context,
+    dnode: &DataNodeRef<'_>,
+    value: &str,
+) -> Result<(), Error> {
+    let value = CString::new(value).unwrap();
+    let ret = unsafe { ffi::lyd_change_term(dnode.as_raw(), value.as_ptr()) };
+    match ret {
+        ffi::LY_ERR::LY_SUCCESS
+        | ffi::LY_ERR::LY_EEXIST
+        | ffi::LY_ERR::LY_ENOT => Ok(()),
+        _ => Err(Error::EditConfig(yang4::Error::new(yang_ctx))),
+    }
+}
+
+// Checks whether the given schema node is a leafref pointing to the target
+// schema node.
+//
+// The target is looked for among all the schema nodes referenced by the
+// leafref path, which also include the nodes in its predicates.
+fn leafref_points_to(snode: &SchemaNode<'_>, target: &SchemaNode<'_>) -> bool {
+    let Some(ltype) = snode.leaf_type() else {
+        return false;
+    };
+    if ltype.base_type() != DataValueType::LeafRef {
+        return false;
+    }
+
+    let leafref = ltype.as_raw() as *mut ffi::lysc_type_leafref;
+    let mut set = std::ptr::null_mut();
+    let ret = unsafe {
+        ffi::lys_find_expr_atoms(
+            snode.as_raw(),
+            (*snode.as_raw()).module,
+            (*leafref).path,
+            (*leafref).prefixes,
+            0,
+            &mut set,
+        )
+    };
+    if ret != ffi::LY_ERR::LY_SUCCESS {
+        return false;
+    }
+
+    let found = unsafe {
+        let count = (*set).count as usize;
+        let snodes = (*set).__bindgen_anon_1.snodes;
+        (0..count).any(|i| *snodes.add(i) == target.as_raw())
+    };
+    unsafe { ffi::ly_set_free(set, None) };
+    found
+}
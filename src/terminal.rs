@@ -18,7 +18,7 @@ use reedline::{
 
 use crate::Cli;
 use crate::error::ParserError;
-use crate::parser::{self, ParsedCommand};
+use crate::parser::{self, ParsedCommand, Word};
 use crate::token::{Commands, TokenKind};
 
 static DEFAULT_PROMPT_INDICATOR: &str = "# ";
@@ -85,12 +85,15 @@ impl Completer for CliCompleter {
     fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
         let cli = self.0.lock().unwrap();
 
-        let last_word = line.split_whitespace().last().unwrap_or(line);
-        let partial = line
-            .chars()
-            .last()
-            .map(|c| !c.is_whitespace())
-            .unwrap_or(false);
+        // Only the text before the cursor is completed.
+        let line = line.get(..pos).unwrap_or(line);
+        let Ok(last_word) = completion_word(line) else {
+            return vec![];
+        };
+        let partial = last_word.is_some();
+        let start = last_word.as_ref().map_or(pos, |word| word.start);
+        let last_word =
+            last_word.as_ref().map_or("", |word| word.value.as_str());
 
         let wd_token_id = cli.session.mode().token(&cli.commands);
         let completions = match parser::parse_command_try(
@@ -125,10 +128,7 @@ impl Completer for CliCompleter {
                 value,
                 description,
                 extra: None,
-                span: Span {
-                    start: if partial { pos - last_word.len() } else { pos },
-                    end: pos,
-                },
+                span: Span { start, end: pos },
                 append_whitespace: true,
                 style: None,
             })
@@ -192,6 +192,15 @@ pub fn reedline_init(
         .with_menu(ReedlineMenu::EngineCompleter(completion_menu))
 }
 
+// Returns the word being completed at the end of the line, if any.
+//
+// The line is split the same way the parser does, so that quoted words are
+// handled consistently.
+fn completion_word(line: &str) -> Result<Option<Word>, ParserError> {
+    let mut words = parser::lex(line)?;
+    Ok(words.pop().filter(|word| word.end == line.len()))
+}
+
 fn complete_add_token(
     commands: &Commands,
     token_id: NodeId,
@@ -203,7 +212,7 @@ fn complete_add_token(
     let token = commands.get_token(token_id);
     if token.kind == TokenKind::Word && !token.matches(word, true) {
         completions.push((token.name.clone(), token.help.clone()));
-    } else if token.kind == TokenKind::String && !partial {
+    } else if token.kind != TokenKind::Word && !partial {
         completions.push((token.name.to_uppercase(), token.help.clone()));
     } else if token.kind == TokenKind::Word && token.matches(word, false) {
         completions.push((token.name.to_lowercase(), token.help.clone()));
@@ -223,7 +232,7 @@ fn complete_add_tokens(
             let token = commands.get_token(token_id);
             if token.kind == TokenKind::Word {
                 Some((token.name.clone(), token.help.clone()))
-            } else if token.kind == TokenKind::String && !partial {
+            } else if token.kind != TokenKind::Word && !partial {
                 Some((token.name.to_uppercase(), token.help.clone()))
             } else {
                 None
@@ -232,3 +241,39 @@ fn complete_add_tokens(
         .sorted()
         .collect()
 }
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(line: &str, pos: usize) -> Option<(String, usize)> {
+        completion_word(&line[..pos])
+            .unwrap()
+            .map(|word| (word.value, word.start))
+    }
+
+    #[test]
+    fn completion_word_end_of_line() {
+        assert_eq!(word("", 0), None);
+        assert_eq!(word("show", 4), Some(("show".to_owned(), 0)));
+        assert_eq!(word("show ", 5), None);
+        assert!(completion_word("show 'a b").is_err());
+        assert_eq!(word("show \"a b\"", 10), Some(("a b".to_owned(), 5)));
+    }
+
+    #[test]
+    fn completion_word_mid_line() {
+        let line = "show bgp neighbor";
+        assert_eq!(word(line, 2), Some(("sh".to_owned(), 0)));
+        assert_eq!(word(line, 4), Some(("show".to_owned(), 0)));
+        assert_eq!(word(line, 5), None);
+        assert_eq!(word(line, 7), Some(("bg".to_owned(), 5)));
+        for pos in 0..=line.len() {
+            if let Some((_, start)) = word(line, pos) {
+                assert!(start <= pos);
+            }
+        }
+    }
+}
@@ -17,6 +17,8 @@ pub struct Commands {
     pub config_root_yang: NodeId,
     pub config_root_internal: NodeId,
     pub config_dflt_internal: NodeId,
+    yang_cmds: bool,
+    yang_index_checked: bool,
 }
 
 pub struct Token {
@@ -25,13 +27,14 @@ pub struct Token {
     pub kind: TokenKind,
     pub argument: Option<String>,
     pub action: Option<Action>,
-    pub node_update: bool,
 }
 
 #[derive(Debug, Eq, PartialEq)]
 pub enum TokenKind {
     Word,
     String,
+    // Matches the rest of the command line.
+    Line,
 }
 
 pub enum Action {
@@ -61,14 +64,57 @@ impl Commands {
             config_root_yang,
             config_root_internal,
             config_dflt_internal,
+            yang_cmds: false,
+            yang_index_checked: false,
         }
     }
 
     pub fn gen_cmds(&mut self) {
-        token_yang::gen_cmds(self);
         token_xml::gen_cmds(self);
     }
 
+    pub fn has_yang_cmds(&self) -> bool {
+        self.yang_cmds
+    }
+
+    // Builds the configuration commands corresponding to the loaded YANG
+    // modules, unless already done.
+    //
+    // The commands are loaded from the given index when it exists. Otherwise,
+    // they're generated only if requested, and then saved to the index.
+    pub fn gen_yang_cmds(&mut self, index_path: Option<&str>, generate: bool) {
+        if self.yang_cmds {
+            return;
+        }
+
+        if let Some(path) = index_path
+            && !self.yang_index_checked
+        {
+            self.yang_index_checked = true;
+            if std::path::Path::new(path).exists() {
+                match token_yang::load_cmds(self, path) {
+                    Ok(()) => {
+                        self.yang_cmds = true;
+                        return;
+                    }
+                    Err(error) => {
+                        eprintln!("Ignoring command index {}: {}", path, error)
+                    }
+                }
+            }
+        }
+
+        if generate {
+            let index = token_yang::gen_cmds(self);
+            self.yang_cmds = true;
+            if let Some(path) = index_path
+                && let Err(error) = index.save(path)
+            {
+                eprintln!("Failed to save command index {}: {}", path, error);
+            }
+        }
+    }
+
     pub fn add_token(&mut self, parent: NodeId, token: Token) -> NodeId {
         let token_id = self.arena.new_node(Some(token));
         parent.append(token_id, &mut self.arena);
@@ -93,7 +139,6 @@ impl Token {
         kind: TokenKind,
         argument: Option<S>,
         action: Option<Action>,
-        node_update: bool,
     ) -> Token {
         Token {
             name: name.into(),
@@ -101,12 +146,11 @@ impl Token {
             kind,
             argument: argument.map(|s| s.into()),
             action,
-            node_update,
         }
     }
 
     pub fn matches(&self, word: &str, exact: bool) -> bool {
-        if self.kind == TokenKind::String {
+        if self.kind != TokenKind::Word {
             // TODO: custom match per token type.
             true
         } else if exact {
@@ -76,14 +76,30 @@ fn parse_tag_token(
         "cmd_exit_config" => internal_commands::cmd_exit_config,
         "cmd_end" => internal_commands::cmd_end,
         "cmd_pwd" => internal_commands::cmd_pwd,
+        "cmd_terminal_timeout" => internal_commands::cmd_terminal_timeout,
+        "cmd_terminal_commit_preview" => {
+            internal_commands::cmd_terminal_commit_preview
+        }
+        "cmd_connect" => internal_commands::cmd_connect,
+        "cmd_switch" => internal_commands::cmd_switch,
         "cmd_top" => internal_commands::cmd_top,
         "cmd_discard" => internal_commands::cmd_discard,
         "cmd_commit" => internal_commands::cmd_commit,
+        "cmd_copy" => internal_commands::cmd_copy,
+        "cmd_rename" => internal_commands::cmd_rename,
+        "cmd_insert" => internal_commands::cmd_insert,
+        "cmd_delete" => internal_commands::cmd_delete,
+        "cmd_edit" => internal_commands::cmd_edit,
+        "cmd_up" => internal_commands::cmd_up,
         "cmd_validate" => internal_commands::cmd_validate,
         "cmd_show_config" => internal_commands::cmd_show_config,
         "cmd_show_config_changes" => internal_commands::cmd_show_config_changes,
         "cmd_show_state" => internal_commands::cmd_show_state,
+        "cmd_show_connections" => internal_commands::cmd_show_connections,
+        "cmd_show_version" => internal_commands::cmd_show_version,
         "cmd_show_yang_modules" => internal_commands::cmd_show_yang_modules,
+        "cmd_show_yang_cache" => internal_commands::cmd_show_yang_cache,
+        "cmd_yang_cache_clear" => internal_commands::cmd_yang_cache_clear,
         "cmd_show_isis_interface" => internal_commands::cmd_show_isis_interface,
         "cmd_show_isis_adjacency" => internal_commands::cmd_show_isis_adjacency,
         "cmd_show_isis_database" => internal_commands::cmd_show_isis_database,
@@ -149,6 +165,7 @@ fn parse_tag_token(
 
     let kind = match kind {
         Some("string") => TokenKind::String,
+        Some("line") => TokenKind::Line,
         Some(_) => panic!("unknown token kind"),
         None => TokenKind::Word,
     };
@@ -156,7 +173,7 @@ fn parse_tag_token(
     let action = callback.map(|callback| Action::Callback(callback));
 
     // Add new token.
-    let token = Token::new(name, help, kind, argument, action, false);
+    let token = Token::new(name, help, kind, argument, action);
 
     // Link new token.
     commands.add_token(parent, token)
@@ -4,18 +4,110 @@
 // SPDX-License-Identifier: MIT
 //
 
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::os::raw::c_void;
 
 use indextree::NodeId;
 use itertools::Itertools;
-use yang4::schema::{DataValueType, SchemaNode, SchemaNodeKind};
+use yang4::context::Context;
+use yang4::ffi;
+use yang4::schema::{
+    DataValueType, SchemaNode, SchemaNodeKind, SchemaPathFormat,
+};
 
 use crate::YANG_CTX;
-use crate::parser::ParsedArgs;
+use crate::error::raw_context;
+use crate::grpc::{CACHE_COMMAND_INDEX_PREFIX, cache_write_file};
+use crate::parser::{self, ParsedArgs};
 use crate::token::{Action, Commands, Token, TokenKind};
 
-pub fn gen_cmds(commands: &mut Commands) {
+// Index of the configuration commands generated from a YANG module set.
+//
+// Each token is recorded along with the schema node it was generated from, in
+// the order the tokens were added, so that the command tree can be rebuilt
+// without walking the whole schema again.
+#[derive(Debug, Default)]
+pub struct CommandIndex {
+    records: Vec<IndexRecord>,
+    positions: HashMap<NodeId, usize>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+struct IndexRecord {
+    // Position of the parent token (1-based), or zero for top-level tokens.
+    parent: usize,
+    is_argument: bool,
+    path: String,
+}
+
+// First line of the command index. Since the index depends on how commands are
+// generated, it's only valid for the holo-cli version that saved it.
+const INDEX_HEADER: &str = concat!("holo-cli ", env!("CARGO_PKG_VERSION"));
+
+// ===== impl CommandIndex =====
+
+impl CommandIndex {
+    fn push(
+        &mut self,
+        parent_token_id: NodeId,
+        token_id: NodeId,
+        snode: &SchemaNode<'_>,
+        is_argument: bool,
+    ) {
+        let parent = self.positions.get(&parent_token_id).copied().unwrap_or(0);
+        self.records.push(IndexRecord {
+            parent,
+            is_argument,
+            path: snode.path(SchemaPathFormat::DATA),
+        });
+        self.positions.insert(token_id, self.records.len());
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), std::io::Error> {
+        let mut data = format!("{}\n", INDEX_HEADER);
+        for record in &self.records {
+            writeln!(data, "{}", record).unwrap();
+        }
+        cache_write_file(path, &data)
+    }
+}
+
+// ===== impl IndexRecord =====
+
+impl IndexRecord {
+    fn parse(line: &str) -> Option<IndexRecord> {
+        let mut fields = line.splitn(3, '\t');
+        let parent = fields.next()?.parse().ok()?;
+        let is_argument = match fields.next()? {
+            "0" => false,
+            "1" => true,
+            _ => return None,
+        };
+        let path = fields.next().filter(|path| !path.is_empty())?;
+        Some(IndexRecord {
+            parent,
+            is_argument,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for IndexRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\t{}\t{}",
+            self.parent, self.is_argument as u8, self.path
+        )
+    }
+}
+
+// ===== global functions =====
+
+pub fn gen_cmds(commands: &mut Commands) -> CommandIndex {
+    let mut index = CommandIndex::default();
+
     // Iterate over top-level YANG nodes.
     let yang_ctx = YANG_CTX.get().unwrap();
     for snode in yang_ctx
@@ -25,12 +117,71 @@ pub fn gen_cmds(commands: &mut Commands) {
         .filter(|snode| snode.is_status_current())
         .sorted_by(|a, b| Ord::cmp(&a.name(), &b.name()))
     {
-        gen_cmds_recursive(commands, snode, commands.config_root_yang);
+        gen_cmds_recursive(
+            commands,
+            &mut index,
+            snode,
+            commands.config_root_yang,
+        );
     }
+
+    index
+}
+
+// Rebuilds the configuration commands from a previously saved index.
+//
+// All schema nodes are resolved before adding any token, so that the command
+// tree is left untouched if the index is stale.
+pub fn load_cmds(commands: &mut Commands, path: &str) -> Result<(), String> {
+    let yang_ctx = YANG_CTX.get().unwrap();
+    let data =
+        std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+    let mut lines = data.lines();
+    if lines.next() != Some(INDEX_HEADER) {
+        return Err("index saved by another version".to_owned());
+    }
+
+    let mut tokens = vec![];
+    for (pos, line) in lines.enumerate() {
+        let record = IndexRecord::parse(line)
+            .filter(|record| record.parent <= pos)
+            .ok_or_else(|| format!("invalid record at line {}", pos + 2))?;
+        let snode = yang_ctx
+            .find_path(&record.path)
+            .map_err(|_| format!("unknown schema node: {}", record.path))?;
+        tokens.push((record, snode));
+    }
+
+    let mut token_ids = vec![];
+    for (record, snode) in tokens {
+        let mut token_id = match record.parent {
+            0 => commands.config_root_yang,
+            parent => token_ids[parent - 1],
+        };
+        let kind = match record.is_argument {
+            true => TokenKind::String,
+            false => TokenKind::Word,
+        };
+        add_token(commands, &mut token_id, &snode, kind, record.is_argument);
+        token_ids.push(token_id);
+    }
+
+    Ok(())
+}
+
+// Returns the path of the command index for the given YANG module set, which
+// is identified by the hash of its modules, revisions and enabled features.
+pub fn command_index_path(dir: &str, yang_ctx: &Context) -> Option<String> {
+    let hash = unsafe { ffi::ly_ctx_get_modules_hash(raw_context(yang_ctx)?) };
+    Some(format!(
+        "{}/{}{:08x}.idx",
+        dir, CACHE_COMMAND_INDEX_PREFIX, hash
+    ))
 }
 
 fn gen_cmds_recursive(
     commands: &mut Commands,
+    index: &mut CommandIndex,
     snode: SchemaNode<'static>,
     parent_token_id: NodeId,
 ) {
@@ -38,7 +189,7 @@ fn gen_cmds_recursive(
 
     // Add tokens for this node.
     if !snode.is_schema_only() {
-        add_tokens(commands, &snode, &mut token_id);
+        add_tokens(commands, index, &snode, &mut token_id);
     }
 
     // Iterate over child nodes (skipping list keys, schema-only nodes and
@@ -50,17 +201,20 @@ fn gen_cmds_recursive(
         .filter(|snode| snode.is_status_current())
         .sorted_by(|a, b| Ord::cmp(&a.name(), &b.name()))
     {
-        gen_cmds_recursive(commands, snode, token_id);
+        gen_cmds_recursive(commands, index, snode, token_id);
     }
 }
 
 fn add_tokens(
     commands: &mut Commands,
+    index: &mut CommandIndex,
     snode: &SchemaNode<'static>,
     token_id: &mut NodeId,
 ) {
     // Add base token corresponding to the schema node.
+    let parent_token_id = *token_id;
     add_token(commands, token_id, snode, TokenKind::Word, false);
+    index.push(parent_token_id, *token_id, snode, false);
 
     match snode.kind() {
         SchemaNodeKind::Leaf | SchemaNodeKind::LeafList
@@ -68,12 +222,16 @@ fn add_tokens(
                 != DataValueType::Empty =>
         {
             // Add input token.
+            let parent_token_id = *token_id;
             add_token(commands, token_id, snode, TokenKind::String, true);
+            index.push(parent_token_id, *token_id, snode, true);
         }
         SchemaNodeKind::List => {
             // Add list keys.
             for snode in snode.list_keys() {
+                let parent_token_id = *token_id;
                 add_token(commands, token_id, &snode, TokenKind::String, true);
+                index.push(parent_token_id, *token_id, &snode, true);
             }
         }
         _ => (),
@@ -92,9 +250,8 @@ fn add_token(
     let argument = if is_argument { Some(name) } else { None };
     let action = is_full_command(snode, is_argument)
         .then(|| Action::ConfigEdit(snode.clone()));
-    let node_update = snode.kind() == SchemaNodeKind::List;
 
-    let token = Token::new(name, help, kind, argument, action, node_update);
+    let token = Token::new(name, help, kind, argument, action);
     *token_id = commands.add_token(*token_id, token);
     snode_set_token_id(snode, *token_id);
 }
@@ -132,6 +289,55 @@ pub fn update_cli_path(
     write!(path, "/{}[{}]", snode.name(), list_keys).unwrap();
 }
 
+// Converts a YANG data path into the equivalent CLI command path.
+//
+// For example, "/ietf-interfaces:interfaces/interface[name='eth0']/enabled" is
+// converted to "interfaces interface eth0 enabled".
+pub fn data_path_to_cli_path(path: &str) -> String {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut quote = None;
+    let mut predicate = false;
+    for c in path.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => token.push(c),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '/') if !predicate => {
+                push_node_token(&mut tokens, &mut token);
+            }
+            (None, '[') => {
+                push_node_token(&mut tokens, &mut token);
+                predicate = true;
+            }
+            (None, ']') => {
+                tokens.push(parser::quote_word(&std::mem::take(&mut token)));
+                predicate = false;
+            }
+            // Skip the key name, keeping only its value.
+            (None, '=') if predicate => token.clear(),
+            (None, c) => token.push(c),
+        }
+    }
+    push_node_token(&mut tokens, &mut token);
+
+    tokens.join(" ")
+}
+
+// Adds the name of a data node to the CLI path, without its module prefix.
+fn push_node_token(tokens: &mut Vec<String>, token: &mut String) {
+    let token = std::mem::take(token);
+    if token.is_empty() {
+        return;
+    }
+    match token.split_once(':') {
+        Some((_, name)) => tokens.push(name.to_owned()),
+        None => tokens.push(token),
+    }
+}
+
+// ===== helper functions =====
+
 // Save token ID in the schema node private pointer.
 fn snode_set_token_id(snode: &SchemaNode<'_>, token_id: NodeId) {
     let btoken_id = Box::new(token_id);
@@ -147,3 +353,66 @@ pub fn snode_get_token_id(snode: &SchemaNode<'_>) -> NodeId {
     snode_set_token_id(snode, token_id);
     token_id
 }
+
+// ===== unit tests =====
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_path_to_cli_path_nodes() {
+        assert_eq!(
+            data_path_to_cli_path("/ietf-system:system/hostname"),
+            "system hostname"
+        );
+        assert_eq!(
+            data_path_to_cli_path(
+                "/ietf-routing:routing/ietf-ospf:ospf/preference"
+            ),
+            "routing ospf preference"
+        );
+    }
+
+    #[test]
+    fn data_path_to_cli_path_keys() {
+        assert_eq!(
+            data_path_to_cli_path(
+                "/ietf-interfaces:interfaces/interface[name='eth0']/enabled"
+            ),
+            "interfaces interface eth0 enabled"
+        );
+        assert_eq!(
+            data_path_to_cli_path(
+                "/a:list[type='x:static'][name='a/b']/leaf-list[.='c d']"
+            ),
+            "list x:static a/b leaf-list \"c d\""
+        );
+        assert_eq!(
+            data_path_to_cli_path("/a:list[name=\"it's\"]"),
+            "list \"it's\""
+        );
+        assert_eq!(data_path_to_cli_path("/a:list[name='']"), "list \"\"");
+    }
+
+    #[test]
+    fn index_record_round_trip() {
+        let record = IndexRecord {
+            parent: 3,
+            is_argument: true,
+            path: "/ietf-interfaces:interfaces/interface/name".to_owned(),
+        };
+        let line = record.to_string();
+        assert_eq!(line, "3\t1\t/ietf-interfaces:interfaces/interface/name");
+        assert_eq!(IndexRecord::parse(&line), Some(record));
+    }
+
+    #[test]
+    fn index_record_invalid() {
+        assert_eq!(IndexRecord::parse(""), None);
+        assert_eq!(IndexRecord::parse("x\t0\t/a:b"), None);
+        assert_eq!(IndexRecord::parse("1\t2\t/a:b"), None);
+        assert_eq!(IndexRecord::parse("1\t0"), None);
+        assert_eq!(IndexRecord::parse("1\t0\t"), None);
+    }
+}